@@ -0,0 +1,49 @@
+//! The default pyramid-on-a-grid scene with the free-fly camera.
+//!
+//! Run with `cargo run --example pyramid`. Debug builds reload
+//! `src/shader.wgsl` whenever it is saved. Controls are read from
+//! `examples/bindings.toml`.
+
+use cgmath::Vector3;
+use wgpu_render_engine::{ActionMap, App, Engine, Material, MeshData, Renderer, Transform};
+
+struct Pyramid;
+
+impl App for Pyramid {
+    fn init(&mut self, renderer: &mut Renderer) {
+        let pyramid = renderer.add_mesh(&MeshData::pyramid());
+        let ground = renderer.add_mesh(&MeshData::ground_plane());
+        let grid = renderer.add_material(Material::grid_ground());
+        renderer.set_mesh_material(ground, Some(grid));
+
+        let scene = renderer.scene_mut();
+        scene.add_node(None, Transform::from_translation(Vector3::new(0.0, 1.0, 0.0)), Some(pyramid));
+        scene.add_node(None, Transform::default(), Some(ground));
+
+        // The highest MSAA level the adapter supports
+        let samples = renderer.supported_sample_counts().last().copied().unwrap_or(1);
+        renderer.set_sample_count(samples);
+
+        let controller = renderer.camera_controller_mut();
+        controller.acceleration = Some(10.0);
+        controller.damping = Some(6.0);
+
+        let bindings = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/bindings.toml");
+        match ActionMap::load(bindings) {
+            Ok(action_map) => renderer.set_action_map(action_map),
+            Err(e) => eprintln!("{}", e),
+        }
+
+        if cfg!(debug_assertions) {
+            let shader = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.wgsl");
+            if let Err(e) = renderer.watch_shader(shader) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+}
+
+fn main() {
+    let engine = pollster::block_on(Engine::new("WGPU Engine", 800, 600));
+    engine.run(Pyramid);
+}
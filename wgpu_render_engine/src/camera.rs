@@ -1,25 +1,150 @@
-use cgmath::{perspective, Matrix4, Point3, Rad, Vector3, InnerSpace, Euler, Deg};
+use std::time::Duration;
+
+use cgmath::{Matrix4, Point3, Vector3, Vector4, InnerSpace, Zero};
 use winit::event::*;
 
+use crate::input::Action;
+use crate::mesh::Aabb;
+
+/// How [`Camera::update`] interprets the controller's input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    /// First-person flight: the mouse turns the camera and the movement keys
+    /// move it.
+    #[default]
+    Fly,
+    /// Circles [`Camera::target`] at [`Camera::distance`]: dragging rotates
+    /// around it, scrolling zooms and the movement keys move the target.
+    Orbit,
+}
+
+/// Maps view space to clip space, with depth in wgpu's 0..1 range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in degrees.
+        fovy: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    Orthographic {
+        /// World units visible from the bottom to the top of the view.
+        height: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    /// Perspective with no far plane. Depth runs from 1 at `znear` towards 0
+    /// at infinity, which spreads float precision evenly over large scenes.
+    ReverseZInfinite {
+        /// Vertical field of view in degrees.
+        fovy: f32,
+        znear: f32,
+    },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective {
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fovy, znear, zfar } => {
+                let f = 1.0 / (fovy.to_radians() * 0.5).tan();
+                let depth = zfar / (znear - zfar);
+                Matrix4::from_cols(
+                    Vector4::new(f / aspect, 0.0, 0.0, 0.0),
+                    Vector4::new(0.0, f, 0.0, 0.0),
+                    Vector4::new(0.0, 0.0, depth, -1.0),
+                    Vector4::new(0.0, 0.0, depth * znear, 0.0),
+                )
+            }
+            Projection::Orthographic { height, znear, zfar } => {
+                let half_height = height * 0.5;
+                let depth = 1.0 / (znear - zfar);
+                Matrix4::from_cols(
+                    Vector4::new(1.0 / (half_height * aspect), 0.0, 0.0, 0.0),
+                    Vector4::new(0.0, 1.0 / half_height, 0.0, 0.0),
+                    Vector4::new(0.0, 0.0, depth, 0.0),
+                    Vector4::new(0.0, 0.0, depth * znear, 1.0),
+                )
+            }
+            Projection::ReverseZInfinite { fovy, znear } => {
+                let f = 1.0 / (fovy.to_radians() * 0.5).tan();
+                Matrix4::from_cols(
+                    Vector4::new(f / aspect, 0.0, 0.0, 0.0),
+                    Vector4::new(0.0, f, 0.0, 0.0),
+                    Vector4::new(0.0, 0.0, 0.0, -1.0),
+                    Vector4::new(0.0, 0.0, znear, 0.0),
+                )
+            }
+        }
+    }
+
+    /// Whether nearer surfaces have greater depth, so the depth buffer is
+    /// cleared to 0 and tested with `Greater`.
+    pub fn reverse_z(&self) -> bool {
+        matches!(self, Projection::ReverseZInfinite { .. })
+    }
+
+    pub fn znear(&self) -> f32 {
+        match *self {
+            Projection::Perspective { znear, .. }
+            | Projection::Orthographic { znear, .. }
+            | Projection::ReverseZInfinite { znear, .. } => znear,
+        }
+    }
+
+    /// The far plane distance, or `None` when there is none.
+    pub fn zfar(&self) -> Option<f32> {
+        match *self {
+            Projection::Perspective { zfar, .. } | Projection::Orthographic { zfar, .. } => Some(zfar),
+            Projection::ReverseZInfinite { .. } => None,
+        }
+    }
+}
+
+/// Axis-aligned views for [`Camera::set_ortho_view`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrthoView {
+    /// Looking down -Y, with -Z at the top of the screen.
+    Top,
+    /// Looking down -Z.
+    Front,
+    /// Looking down -X, from the +X side.
+    Side,
+}
+
 #[derive(Debug)]
 pub struct Camera {
     pub position: Point3<f32>,
     pub direction: Vector3<f32>,
-    up: Vector3<f32>,
     aspect: f32,
-    fovy: f32,
-    znear: f32,
-    zfar: f32,
+    projection: Projection,
     pub yaw: f32,
     pub pitch: f32,
+    mode: CameraMode,
+    /// The point an orbiting camera circles, kept `distance` ahead of the
+    /// camera in both modes.
+    pub target: Point3<f32>,
+    pub distance: f32,
+    /// World-space velocity in units per second, eased towards the
+    /// controller's input by [`Camera::update`].
+    velocity: Vector3<f32>,
 }
 
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct CameraUniform {
-    view_proj: [[f32; 4]; 4],
-    view_position: [f32; 4], // Change to [f32; 4] to ensure 16-byte alignment
+crate::wgsl_struct! {
+    pub struct CameraUniform {
+        view_proj: [[f32; 4]; 4],
+        view_position: [f32; 3],
+    }
 }
 
 impl Camera {
@@ -27,48 +152,200 @@ impl Camera {
         Self {
             position: Point3::new(0.0, 1.0, 2.0),
             direction: Vector3::new(0.0, 0.0, -1.0),
-            up: Vector3::new(0.0, 1.0, 0.0),
             aspect: width as f32 / height as f32,
-            fovy: 45.0,
-            znear: 0.1,
-            zfar: 100.0,
+            projection: Projection::default(),
             yaw: -90.0, // Start facing negative Z
             pitch: 0.0,
+            mode: CameraMode::Fly,
+            target: Point3::new(0.0, 1.0, 0.0),
+            distance: 2.0,
+            velocity: Vector3::zero(),
         }
     }
 
-    pub fn build_view_projection_matrix(&self) -> CameraUniform {
-    let view = Matrix4::look_to_rh(self.position, self.direction, self.up);
-    let proj = perspective(Rad(self.fovy.to_radians()), self.aspect, self.znear, self.zfar);
-    CameraUniform {
-        view_proj: (proj * view).into(),
-        view_position: [self.position.x, self.position.y, self.position.z, 0.0], // Add 0.0 as the fourth component
+    /// Places the camera at `position` looking along `direction`, keeping
+    /// `yaw` and `pitch` in sync so [`Camera::update`] preserves the view.
+    pub fn look_to(&mut self, position: Point3<f32>, direction: Vector3<f32>) {
+        let direction = direction.normalize();
+        self.position = position;
+        self.direction = direction;
+        self.yaw = direction.z.atan2(direction.x).to_degrees();
+        self.pitch = direction.y.clamp(-1.0, 1.0).asin().to_degrees();
+        self.target = position + direction * self.distance;
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
     }
+
+    /// Switches modes without moving the camera. Orbiting starts around the
+    /// point `distance` ahead of it.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+        self.target = self.position + self.direction * self.distance;
+        self.velocity = Vector3::zero();
+    }
+
+    /// Targets the center of `bounds` from far enough away to fit it in view,
+    /// keeping the current viewing direction and mode.
+    pub fn frame(&mut self, bounds: &Aabb) {
+        let radius = bounds.radius();
+        let znear = self.projection.znear();
+        match &mut self.projection {
+            Projection::Perspective { fovy, .. } | Projection::ReverseZInfinite { fovy, .. } => {
+                let half_fovy = (*fovy * 0.5).to_radians();
+                let half_fovx = (half_fovy.tan() * self.aspect).atan();
+                self.distance = (radius / half_fovy.min(half_fovx).sin()).max(znear * 2.0);
+            }
+            Projection::Orthographic { height, .. } => {
+                *height = 2.0 * radius * (1.0 / self.aspect).max(1.0);
+                self.distance = radius + znear * 2.0;
+            }
+        }
+        self.target = bounds.center();
+        self.position = self.target - self.direction * self.distance;
+        self.velocity = Vector3::zero();
+    }
+
+    pub fn projection(&self) -> &Projection {
+        &self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Sets the vertical field of view in degrees and the clip plane distances.
+    pub fn set_perspective(&mut self, fovy: f32, znear: f32, zfar: f32) {
+        self.projection = Projection::Perspective { fovy, znear, zfar };
+    }
+
+    /// Shows `height` world units from the bottom to the top of the view.
+    pub fn set_orthographic(&mut self, height: f32, znear: f32, zfar: f32) {
+        self.projection = Projection::Orthographic { height, znear, zfar };
+    }
+
+    /// Sets a reverse-Z perspective projection with no far plane.
+    pub fn set_reverse_z_infinite(&mut self, fovy: f32, znear: f32) {
+        self.projection = Projection::ReverseZInfinite { fovy, znear };
+    }
+
+    /// Looks at [`Camera::target`] along an axis with an orthographic
+    /// projection. Coming from a perspective projection, the height is chosen
+    /// so the target plane keeps its size on screen.
+    pub fn set_ortho_view(&mut self, view: OrthoView) {
+        let (yaw, pitch) = match view {
+            OrthoView::Top => (-90.0, -90.0),
+            OrthoView::Front => (-90.0, 0.0),
+            OrthoView::Side => (180.0, 0.0),
+        };
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.direction = direction_from_angles(yaw, pitch);
+        self.position = self.target - self.direction * self.distance;
+        self.velocity = Vector3::zero();
+        self.projection = match self.projection {
+            Projection::Orthographic { .. } => self.projection,
+            Projection::Perspective { fovy, znear, .. } | Projection::ReverseZInfinite { fovy, znear } => {
+                Projection::Orthographic {
+                    height: 2.0 * self.distance * (fovy.to_radians() * 0.5).tan(),
+                    znear,
+                    zfar: self.projection.zfar().unwrap_or(0.0).max(self.distance * 2.0),
+                }
+            }
+        };
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    /// Horizontal unit vector to the right of the view, derived from `yaw` so
+    /// it stays defined when looking straight up or down.
+    fn right(&self) -> Vector3<f32> {
+        let yaw = self.yaw.to_radians();
+        Vector3::new(-yaw.sin(), 0.0, yaw.cos())
+    }
+
+    pub fn build_view_projection_matrix(&self) -> CameraUniform {
+    let up = self.right().cross(self.direction);
+    let view = Matrix4::look_to_rh(self.position, self.direction, up);
+    let proj = self.projection.matrix(self.aspect);
+    CameraUniform::new((proj * view).into(), self.position.into())
 }
 
-    pub fn update(&mut self, controller: &CameraController) {
-        // Update direction based on mouse movement
-        self.yaw += controller.rotate_horizontal;
-        self.pitch += controller.rotate_vertical;
+    /// View-projection matrix with the camera at the origin, for drawing what
+    /// is infinitely far away. Orthographic cameras get a 45 degree
+    /// perspective instead, so the sky still varies across the view.
+    pub fn rotation_projection_matrix(&self) -> Matrix4<f32> {
+        let up = self.right().cross(self.direction);
+        let view = Matrix4::look_to_rh(Point3::new(0.0, 0.0, 0.0), self.direction, up);
+        let fovy = match self.projection {
+            Projection::Perspective { fovy, .. } | Projection::ReverseZInfinite { fovy, .. } => fovy,
+            Projection::Orthographic { .. } => 45.0,
+        };
+        let proj = Projection::Perspective {
+            fovy,
+            znear: 0.1,
+            zfar: 1.0,
+        };
+        proj.matrix(self.aspect) * view
+    }
 
-        // Clamp pitch to prevent camera flipping
-        self.pitch = self.pitch.clamp(-89.0, 89.0);
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    /// Applies the controller's rotation and moves the camera for a frame
+    /// lasting `dt`.
+    pub fn update(&mut self, controller: &CameraController, dt: Duration) {
+        // Update direction based on mouse movement. The pitch is clamped to
+        // prevent the camera flipping.
+        if controller.rotate_horizontal != 0.0 || controller.rotate_vertical != 0.0 {
+            self.yaw += controller.rotate_horizontal;
+            self.pitch = (self.pitch + controller.rotate_vertical).clamp(-89.0, 89.0);
+        }
 
         // Compute new direction vector
-        let direction = Vector3::new(
-            self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
-            self.pitch.to_radians().sin(),
-            self.yaw.to_radians().sin() * self.pitch.to_radians().cos()
-        ).normalize();
+        let direction = direction_from_angles(self.yaw, self.pitch);
         self.direction = direction;
 
-        // Compute camera right vector
-        let right = direction.cross(self.up).normalize();
-        
-        // Update position based on movement
-        self.position += self.direction * (controller.amount_forward - controller.amount_backward) * controller.speed;
-        self.position += right * (controller.amount_right - controller.amount_left) * controller.speed;
-        self.position.y += (controller.amount_up - controller.amount_down) * controller.speed;
+        let right = self.right();
+
+        // Ease the velocity towards the input, then move by it
+        let target = (self.direction * (controller.amount_forward - controller.amount_backward)
+            + right * (controller.amount_right - controller.amount_left)
+            + Vector3::unit_y() * (controller.amount_up - controller.amount_down))
+            * controller.speed;
+        let dt = dt.as_secs_f32();
+        let rate = if target.is_zero() { controller.damping } else { controller.acceleration };
+        self.velocity = match rate {
+            // Exponential easing covers the same fraction of the gap per
+            // second whatever the frame rate.
+            Some(rate) => self.velocity + (target - self.velocity) * (1.0 - (-rate * dt).exp()),
+            None => target,
+        };
+        // Pan within the view plane, faster the further out the camera is
+        let up = right.cross(direction);
+        let pan = (up * controller.pan_vertical - right * controller.pan_horizontal)
+            * controller.pan_sensitivity
+            * self.distance;
+
+        match self.mode {
+            CameraMode::Fly => {
+                self.position += self.velocity * dt + pan;
+                self.target = self.position + direction * self.distance;
+            }
+            CameraMode::Orbit => {
+                let zoom = (1.0 - controller.zoom_sensitivity).powf(controller.scroll);
+                self.distance = (self.distance * zoom).max(self.projection.znear() * 2.0);
+                if let Projection::Orthographic { height, .. } = &mut self.projection {
+                    *height *= zoom;
+                }
+                self.target += self.velocity * dt + pan;
+                self.position = self.target - direction * self.distance;
+            }
+        }
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -76,6 +353,11 @@ impl Camera {
     }
 }
 
+fn direction_from_angles(yaw: f32, pitch: f32) -> Vector3<f32> {
+    let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+    Vector3::new(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos()).normalize()
+}
+
 pub struct CameraController {
     pub amount_left: f32,
     pub amount_right: f32,
@@ -85,9 +367,32 @@ pub struct CameraController {
     pub amount_down: f32,
     pub rotate_horizontal: f32,
     pub rotate_vertical: f32,
+    /// Mouse movement made while panning, in the same units as the motion events.
+    pub pan_horizontal: f32,
+    pub pan_vertical: f32,
+    /// Scroll lines since the last reset; positive zooms an orbiting camera in.
     pub scroll: f32,
+    /// Whether [`Action::Rotate`] is held.
+    pub rotating: bool,
+    /// Whether [`Action::Pan`] is held.
+    pub panning: bool,
+    /// Whether the mouse is captured, so every movement turns the camera
+    /// without holding a button.
+    pub captured: bool,
+    /// Movement speed in units per second.
     pub speed: f32,
+    /// Degrees of rotation per unit of mouse movement.
     pub sensitivity: f32,
+    /// How quickly the camera speeds up towards `speed` while a movement key
+    /// is held, as a rate per second. `None` reaches full speed at once.
+    pub acceleration: Option<f32>,
+    /// How quickly the camera slows down once every movement key is
+    /// released, as a rate per second. `None` stops at once.
+    pub damping: Option<f32>,
+    /// Fraction of the orbit distance closed per scroll line.
+    pub zoom_sensitivity: f32,
+    /// Fraction of the orbit distance panned per unit of mouse movement.
+    pub pan_sensitivity: f32,
 }
 
 impl CameraController {
@@ -101,50 +406,113 @@ impl CameraController {
             amount_down: 0.0,
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
+            pan_horizontal: 0.0,
+            pan_vertical: 0.0,
             scroll: 0.0,
+            rotating: false,
+            panning: false,
+            captured: false,
             speed,
             sensitivity,
+            acceleration: None,
+            damping: None,
+            zoom_sensitivity: 0.1,
+            pan_sensitivity: 0.002,
         }
     }
 
-    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
-        let amount = if state == ElementState::Pressed { 1.0 } else { 0.0 };
-        match key {
-            VirtualKeyCode::W | VirtualKeyCode::Up => {
-                self.amount_forward = amount;
-                true
-            }
-            VirtualKeyCode::S | VirtualKeyCode::Down => {
-                self.amount_backward = amount;
-                true
-            }
-            VirtualKeyCode::A | VirtualKeyCode::Left => {
-                self.amount_left = amount;
-                true
-            }
-            VirtualKeyCode::D | VirtualKeyCode::Right => {
-                self.amount_right = amount;
-                true
-            }
-            VirtualKeyCode::Space => {
-                self.amount_up = amount;
-                true
+    /// Eases movement in and out at the given rates instead of starting and
+    /// stopping instantly.
+    pub fn with_smoothing(self, acceleration: f32, damping: f32) -> Self {
+        Self {
+            acceleration: Some(acceleration),
+            damping: Some(damping),
+            ..self
+        }
+    }
+
+    /// Starts or stops the camera part of `action`. Returns `false` for
+    /// actions the controller does not handle.
+    pub fn process_action(&mut self, action: Action, state: ElementState) -> bool {
+        let pressed = state == ElementState::Pressed;
+        let amount = if pressed { 1.0 } else { 0.0 };
+        match action {
+            Action::MoveForward => self.amount_forward = amount,
+            Action::MoveBackward => self.amount_backward = amount,
+            Action::MoveLeft => self.amount_left = amount,
+            Action::MoveRight => self.amount_right = amount,
+            Action::MoveUp => self.amount_up = amount,
+            Action::MoveDown => self.amount_down = amount,
+            Action::Rotate => self.rotating = pressed,
+            Action::Pan => self.panning = pressed,
+            Action::ToggleCapture => {
+                if pressed {
+                    self.captured = !self.captured;
+                }
             }
-            VirtualKeyCode::LShift => {
-                self.amount_down = amount;
-                true
+            Action::ReleaseCapture => {
+                if !self.captured {
+                    return false;
+                }
+                if pressed {
+                    self.captured = false;
+                }
             }
-            _ => false,
+            _ => return false,
         }
+        true
     }
 
+    /// Accumulates mouse movement until the next [`CameraController::reset_mouse_movement`],
+    /// so several motion events in one frame all count. Movement while the pan
+    /// button is held pans; otherwise it turns the camera only while the rotate
+    /// button is held or the mouse is captured.
     pub fn process_mouse_movement(&mut self, delta_x: f32, delta_y: f32) {
-    self.rotate_horizontal = delta_x * self.sensitivity;
-    self.rotate_vertical = -delta_y * self.sensitivity;
+    if self.panning {
+        self.pan_horizontal += delta_x;
+        self.pan_vertical += delta_y;
+    } else if self.rotating || self.captured {
+        self.rotate_horizontal += delta_x * self.sensitivity;
+        self.rotate_vertical += -delta_y * self.sensitivity;
+    }
 }
 
+    /// Whether the cursor should be grabbed and hidden: while captured or
+    /// while dragging with either button.
+    pub fn wants_cursor_grab(&self) -> bool {
+        self.captured || self.rotating || self.panning
+    }
+
+    /// Releases every key, button and the capture, e.g. when the window
+    /// loses focus and would miss the release events.
+    pub fn release(&mut self) {
+        self.amount_left = 0.0;
+        self.amount_right = 0.0;
+        self.amount_forward = 0.0;
+        self.amount_backward = 0.0;
+        self.amount_up = 0.0;
+        self.amount_down = 0.0;
+        self.rotating = false;
+        self.panning = false;
+        self.captured = false;
+        self.reset_mouse_movement();
+    }
+
+    /// Accumulates wheel movement, in lines. Pixel deltas from touchpads are
+    /// converted at 50 pixels per line.
+    pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+        self.scroll += match delta {
+            MouseScrollDelta::LineDelta(_, y) => *y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+        };
+    }
+
+    /// Clears the rotation, pan and scroll accumulated this frame.
     pub fn reset_mouse_movement(&mut self) {
         self.rotate_horizontal = 0.0;
         self.rotate_vertical = 0.0;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+        self.scroll = 0.0;
     }
 }
@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use winit::{
+    event::{DeviceEvent, Event, WindowEvent},
+    event_loop::EventLoop,
+    window::{CursorGrabMode, Window, WindowBuilder},
+};
+
+use crate::renderer::Renderer;
+
+/// Hooks an application implements to drive the engine's main loop.
+///
+/// Every hook has a default, so an empty `impl App for MyApp {}` opens a
+/// window and renders the renderer's scene with the built-in camera controls.
+pub trait App: 'static {
+    /// Called once, after the window and renderer exist and before the first frame.
+    fn init(&mut self, _renderer: &mut Renderer) {}
+
+    /// Called once per frame before the renderer updates its camera and
+    /// transforms. `dt` is the time since the previous frame.
+    fn update(&mut self, _renderer: &mut Renderer, _dt: Duration) {}
+
+    /// Called once per frame to draw. Override to wrap or replace the default frame.
+    fn render(&mut self, renderer: &mut Renderer) -> Result<(), wgpu::SurfaceError> {
+        renderer.render()
+    }
+
+    /// Called for every window event before the renderer sees it.
+    /// Return `true` to mark the event as handled.
+    fn input(&mut self, _renderer: &mut Renderer, _event: &WindowEvent) -> bool {
+        false
+    }
+}
+
+/// Owns the window, event loop and renderer, and runs an [`App`] on top of them.
+pub struct Engine {
+    event_loop: EventLoop<()>,
+    window: Window,
+    renderer: Renderer,
+}
+
+impl Engine {
+    pub async fn new(title: &str, width: u32, height: u32) -> Self {
+        let event_loop = EventLoop::new();
+        let window = WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(winit::dpi::LogicalSize::new(width, height))
+            .build(&event_loop)
+            .unwrap();
+
+        let renderer = Renderer::new(&window).await;
+
+        Self {
+            event_loop,
+            window,
+            renderer,
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    pub fn renderer_mut(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+
+    /// Runs the event loop until the window is closed. Never returns.
+    pub fn run<A: App>(self, mut app: A) -> ! {
+        let Self {
+            event_loop,
+            window,
+            mut renderer,
+        } = self;
+
+        app.init(&mut renderer);
+        let mut last_frame = Instant::now();
+        let mut focused = true;
+        let mut cursor_grabbed = false;
+
+        event_loop.run(move |event, _, control_flow| match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if let WindowEvent::Focused(is_focused) = event {
+                    focused = *is_focused;
+                    if !focused {
+                        renderer.release_input();
+                    }
+                }
+                if !(app.input(&mut renderer, event) || renderer.input(event)) {
+                    match event {
+                        WindowEvent::CloseRequested => control_flow.set_exit(),
+                        WindowEvent::Resized(physical_size) => {
+                            renderer.resize(*physical_size);
+                        }
+                        _ => {}
+                    }
+                }
+                let grab = focused && renderer.camera_controller().wants_cursor_grab();
+                if grab != cursor_grabbed {
+                    cursor_grabbed = grab;
+                    set_cursor_grab(&window, grab);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } if focused => {
+                renderer.process_mouse_movement(delta.0 as f32, delta.1 as f32);
+            }
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                let now = Instant::now();
+                let dt = now - last_frame;
+                last_frame = now;
+                app.update(&mut renderer, dt);
+                renderer.update(dt);
+                match app.render(&mut renderer) {
+                    Ok(_) => {}
+                    Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size),
+                    Err(wgpu::SurfaceError::OutOfMemory) => control_flow.set_exit(),
+                    Err(e) => eprintln!("{:?}", e),
+                }
+            }
+            Event::MainEventsCleared => {
+                window.request_redraw();
+            }
+            _ => {}
+        })
+    }
+}
+
+/// Grabs and hides the cursor, or releases and shows it.
+///
+/// `Locked` keeps the cursor in place but is unsupported on Windows and X11,
+/// which fall back to `Confined`. If neither works the cursor stays visible,
+/// so it can still be seen when it leaves the window.
+fn set_cursor_grab(window: &Window, grab: bool) {
+    if !grab {
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+        window.set_cursor_visible(true);
+        return;
+    }
+    let result = window
+        .set_cursor_grab(CursorGrabMode::Locked)
+        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+    match result {
+        Ok(()) => window.set_cursor_visible(false),
+        Err(e) => eprintln!("Failed to grab cursor: {}", e),
+    }
+}
@@ -0,0 +1,536 @@
+//! Image-based lighting from equirectangular HDR environment maps.
+//!
+//! [`EnvironmentData`] decodes a Radiance `.hdr` image. Uploading it with
+//! [`Renderer::set_environment`] converts it to a mipmapped cubemap and
+//! precomputes, in compute passes:
+//!
+//! - an irradiance cubemap: the diffuse light arriving around each direction,
+//! - a prefiltered specular cubemap, whose mip levels hold the reflections
+//!   of increasingly rough surfaces,
+//! - a BRDF lookup table for the split-sum approximation, shared by every
+//!   environment.
+//!
+//! The main shader samples them in place of the flat ambient term.
+//!
+//! [`Renderer::set_environment`]: crate::Renderer::set_environment
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use wgpu::util::DeviceExt;
+
+use crate::texture::TextureError;
+
+/// Format of the environment cubemaps and the BRDF lookup table.
+pub const ENVIRONMENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Width of each face of the irradiance cubemap. Irradiance varies slowly,
+/// so a few texels per face suffice.
+const IRRADIANCE_SIZE: u32 = 32;
+
+/// Width of the top level of the prefiltered specular cubemap.
+const PREFILTERED_SIZE: u32 = 128;
+
+/// Mip levels of the prefiltered cubemap, from mirror-like to fully rough.
+const PREFILTERED_LEVELS: u32 = 5;
+
+/// Width and height of the BRDF lookup table.
+const BRDF_LUT_SIZE: u32 = 128;
+
+/// Width and height of the tile each workgroup of `ibl.wgsl` writes.
+const WORKGROUP_SIZE: u32 = 8;
+
+crate::wgsl_struct! {
+    /// Roughness the prefiltered level being written is convolved for.
+    pub(crate) struct PrefilterUniform {
+        roughness: f32,
+    }
+}
+
+/// An equirectangular HDR image on the CPU, ready to be uploaded with
+/// [`Renderer::set_environment`].
+///
+/// [`Renderer::set_environment`]: crate::Renderer::set_environment
+#[derive(Debug, Clone)]
+pub struct EnvironmentData {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed linear RGBA rows, top row first. The top row looks
+    /// straight up and the left edge looks towards -X.
+    pub rgba: Vec<f32>,
+}
+
+impl EnvironmentData {
+    /// Loads a Radiance `.hdr` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| TextureError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::decode(&bytes)
+    }
+
+    /// Decodes Radiance `.hdr` bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self, TextureError> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Hdr)
+            .map_err(TextureError::Decode)?
+            .to_rgba32f();
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+        })
+    }
+
+    /// The image shrunk to at most `max_size` texels in either dimension,
+    /// such as the device's `max_texture_dimension_2d`, by halving it with a
+    /// box filter as often as needed. Borrowed when it already fits.
+    pub fn fit_within(&self, max_size: u32) -> Cow<'_, Self> {
+        let mut image = Cow::Borrowed(self);
+        while image.width > max_size || image.height > max_size {
+            image = Cow::Owned(image.halved());
+        }
+        image
+    }
+
+    /// Averages each 2x2 block of texels, repeating the last row or column
+    /// of an odd-sized image.
+    fn halved(&self) -> Self {
+        let width = self.width.div_ceil(2);
+        let height = self.height.div_ceil(2);
+        let texel = |x: u32, y: u32| {
+            let index = (y.min(self.height - 1) * self.width + x.min(self.width - 1)) as usize * 4;
+            &self.rgba[index..index + 4]
+        };
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let block = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| texel(x * 2 + dx, y * 2 + dy));
+                rgba.extend((0..4).map(|c| block.iter().map(|texel| texel[c]).sum::<f32>() / 4.0));
+            }
+        }
+        Self { width, height, rgba }
+    }
+
+    /// Width of each cubemap face: a quarter of the image width, the span
+    /// of one face around the horizon, rounded up to a power of two.
+    pub fn cube_size(&self) -> u32 {
+        (self.width / 4).next_power_of_two().clamp(16, 1024)
+    }
+}
+
+/// An environment uploaded with [`Renderer::set_environment`], along with
+/// the lighting precomputed from it.
+///
+/// [`Renderer::set_environment`]: crate::Renderer::set_environment
+pub struct Environment {
+    cube: wgpu::Texture,
+    cube_view: wgpu::TextureView,
+    irradiance_view: wgpu::TextureView,
+    prefiltered: wgpu::Texture,
+    prefiltered_view: wgpu::TextureView,
+    brdf_lut_view: wgpu::TextureView,
+}
+
+impl Environment {
+    /// Black cubemaps and an empty lookup table, bound while no environment is set.
+    pub(crate) fn placeholder(device: &wgpu::Device) -> Self {
+        let (cube, cube_view) = create_cube(device, "Environment Cubemap", 1, 1);
+        let (_, irradiance_view) = create_cube(device, "Irradiance Cubemap", 1, 1);
+        let (prefiltered, prefiltered_view) = create_cube(device, "Prefiltered Cubemap", 1, 1);
+        let brdf_lut = create_brdf_lut(device, 1);
+        Self {
+            cube,
+            cube_view,
+            irradiance_view,
+            prefiltered,
+            prefiltered_view,
+            brdf_lut_view: brdf_lut.create_view(&wgpu::TextureViewDescriptor::default()),
+        }
+    }
+
+    /// Width of each face of the environment cubemap.
+    pub fn cube_size(&self) -> u32 {
+        self.cube.width()
+    }
+
+    /// The environment as a mipmapped cubemap.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.cube_view
+    }
+
+    pub fn irradiance_view(&self) -> &wgpu::TextureView {
+        &self.irradiance_view
+    }
+
+    pub fn prefiltered_view(&self) -> &wgpu::TextureView {
+        &self.prefiltered_view
+    }
+
+    /// Highest mip level of the prefiltered cubemap, sampled for roughness 1.
+    pub fn max_reflection_lod(&self) -> f32 {
+        (self.prefiltered.mip_level_count() - 1) as f32
+    }
+
+    pub fn brdf_lut_view(&self) -> &wgpu::TextureView {
+        &self.brdf_lut_view
+    }
+}
+
+/// The compute pipelines that precompute an [`Environment`], and the BRDF
+/// lookup table they compute once.
+pub(crate) struct IblGenerator {
+    equirect_layout: wgpu::BindGroupLayout,
+    downsample_layout: wgpu::BindGroupLayout,
+    irradiance_layout: wgpu::BindGroupLayout,
+    prefilter_layout: wgpu::BindGroupLayout,
+    equirect_pipeline: wgpu::ComputePipeline,
+    downsample_pipeline: wgpu::ComputePipeline,
+    irradiance_pipeline: wgpu::ComputePipeline,
+    prefilter_pipeline: wgpu::ComputePipeline,
+    sampler: wgpu::Sampler,
+    brdf_lut: wgpu::Texture,
+}
+
+impl IblGenerator {
+    /// Builds the pipelines and computes the BRDF lookup table. Requires
+    /// compute shaders.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("IBL Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("ibl.wgsl"))),
+        });
+        let cube_layout = |label, entries: &[wgpu::BindGroupLayoutEntry]| {
+            let mut all = vec![storage_entry(1, wgpu::TextureViewDimension::D2Array)];
+            all.extend_from_slice(entries);
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &all,
+            })
+        };
+        let equirect_layout = cube_layout(
+            "Equirect Bind Group Layout",
+            &[texture_entry(0, wgpu::TextureViewDimension::D2, false)],
+        );
+        let downsample_layout = cube_layout(
+            "Downsample Bind Group Layout",
+            &[texture_entry(2, wgpu::TextureViewDimension::Cube, true), sampler_entry(4)],
+        );
+        let irradiance_layout = cube_layout(
+            "Irradiance Bind Group Layout",
+            &[texture_entry(3, wgpu::TextureViewDimension::Cube, true), sampler_entry(4)],
+        );
+        let prefilter_layout = cube_layout(
+            "Prefilter Bind Group Layout",
+            &[
+                texture_entry(3, wgpu::TextureViewDimension::Cube, true),
+                sampler_entry(4),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let brdf_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("BRDF Bind Group Layout"),
+            entries: &[storage_entry(6, wgpu::TextureViewDimension::D2)],
+        });
+
+        let pipeline = |layout: &wgpu::BindGroupLayout, entry_point| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("IBL Pipeline Layout"),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+        let equirect_pipeline = pipeline(&equirect_layout, "equirect_to_cube");
+        let downsample_pipeline = pipeline(&downsample_layout, "downsample");
+        let irradiance_pipeline = pipeline(&irradiance_layout, "irradiance");
+        let prefilter_pipeline = pipeline(&prefilter_layout, "prefilter_specular");
+        let brdf_pipeline = pipeline(&brdf_layout, "integrate_brdf");
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let brdf_lut = create_brdf_lut(device, BRDF_LUT_SIZE);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("BRDF Bind Group"),
+            layout: &brdf_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(
+                    &brdf_lut.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            }],
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("BRDF Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("BRDF Pass"),
+            });
+            compute_pass.set_pipeline(&brdf_pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            let groups = BRDF_LUT_SIZE.div_ceil(WORKGROUP_SIZE);
+            compute_pass.dispatch_workgroups(groups, groups, 1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        Self {
+            equirect_layout,
+            downsample_layout,
+            irradiance_layout,
+            prefilter_layout,
+            equirect_pipeline,
+            downsample_pipeline,
+            irradiance_pipeline,
+            prefilter_pipeline,
+            sampler,
+            brdf_lut,
+        }
+    }
+
+    /// Uploads `data`, downsampled to fit the device's texture size limit,
+    /// and precomputes its lighting.
+    pub fn generate(&self, device: &wgpu::Device, queue: &wgpu::Queue, data: &EnvironmentData) -> Environment {
+        let data = data.fit_within(device.limits().max_texture_dimension_2d);
+        let source = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Equirect Texture"),
+                size: wgpu::Extent3d {
+                    width: data.width,
+                    height: data.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            bytemuck::cast_slice(&data.rgba),
+        );
+        let size = data.cube_size();
+        let levels = 32 - size.leading_zeros();
+        let (cube, cube_view) = create_cube(device, "Environment Cubemap", size, levels);
+        let (irradiance, irradiance_view) = create_cube(device, "Irradiance Cubemap", IRRADIANCE_SIZE, 1);
+        let (prefiltered, prefiltered_view) =
+            create_cube(device, "Prefiltered Cubemap", PREFILTERED_SIZE, PREFILTERED_LEVELS);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("IBL Encoder"),
+        });
+        let dispatch = |encoder: &mut wgpu::CommandEncoder,
+                        pipeline: &wgpu::ComputePipeline,
+                        layout,
+                        entries: &[wgpu::BindGroupEntry],
+                        size: u32| {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("IBL Bind Group"),
+                layout,
+                entries,
+            });
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("IBL Pass"),
+            });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            let groups = size.div_ceil(WORKGROUP_SIZE);
+            compute_pass.dispatch_workgroups(groups, groups, 6);
+        };
+
+        let source_view = source.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = wgpu::BindGroupEntry {
+            binding: 4,
+            resource: wgpu::BindingResource::Sampler(&self.sampler),
+        };
+        dispatch(
+            &mut encoder,
+            &self.equirect_pipeline,
+            &self.equirect_layout,
+            &[texture_binding(0, &source_view), texture_binding(1, &array_view(&cube, 0))],
+            size,
+        );
+        for level in 1..levels {
+            let previous_view = cube.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Cubemap Previous Level View"),
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                base_mip_level: level - 1,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+            dispatch(
+                &mut encoder,
+                &self.downsample_pipeline,
+                &self.downsample_layout,
+                &[
+                    texture_binding(1, &array_view(&cube, level)),
+                    texture_binding(2, &previous_view),
+                    sampler.clone(),
+                ],
+                size >> level,
+            );
+        }
+
+        let irradiance_output = array_view(&irradiance, 0);
+        dispatch(
+            &mut encoder,
+            &self.irradiance_pipeline,
+            &self.irradiance_layout,
+            &[
+                texture_binding(1, &irradiance_output),
+                texture_binding(3, &cube_view),
+                sampler.clone(),
+            ],
+            IRRADIANCE_SIZE,
+        );
+        for level in 0..PREFILTERED_LEVELS {
+            let roughness = level as f32 / (PREFILTERED_LEVELS - 1) as f32;
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Prefilter Buffer"),
+                contents: bytemuck::bytes_of(&PrefilterUniform::new(roughness)),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let output = array_view(&prefiltered, level);
+            dispatch(
+                &mut encoder,
+                &self.prefilter_pipeline,
+                &self.prefilter_layout,
+                &[
+                    texture_binding(1, &output),
+                    texture_binding(3, &cube_view),
+                    sampler.clone(),
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+                PREFILTERED_SIZE >> level,
+            );
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        Environment {
+            cube,
+            cube_view,
+            irradiance_view,
+            prefiltered,
+            prefiltered_view,
+            brdf_lut_view: self.brdf_lut.create_view(&wgpu::TextureViewDescriptor::default()),
+        }
+    }
+}
+
+/// Creates a cubemap with `levels` mip levels and a cube view of all of them.
+fn create_cube(device: &wgpu::Device, label: &str, size: u32, levels: u32) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: levels,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ENVIRONMENT_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    (texture, view)
+}
+
+fn create_brdf_lut(device: &wgpu::Device, size: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("BRDF Lookup Table"),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ENVIRONMENT_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        view_formats: &[],
+    })
+}
+
+/// The six faces of one mip level of a cubemap, for writing from compute.
+fn array_view(cube: &wgpu::Texture, level: u32) -> wgpu::TextureView {
+    cube.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("Cubemap Level View"),
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        base_mip_level: level,
+        mip_level_count: Some(1),
+        ..Default::default()
+    })
+}
+
+fn texture_binding(binding: u32, view: &wgpu::TextureView) -> wgpu::BindGroupEntry<'_> {
+    wgpu::BindGroupEntry {
+        binding,
+        resource: wgpu::BindingResource::TextureView(view),
+    }
+}
+
+fn texture_entry(binding: u32, view_dimension: wgpu::TextureViewDimension, filterable: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn storage_entry(binding: u32, view_dimension: wgpu::TextureViewDimension) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access: wgpu::StorageTextureAccess::WriteOnly,
+            format: ENVIRONMENT_FORMAT,
+            view_dimension,
+        },
+        count: None,
+    }
+}
+
+fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
@@ -0,0 +1,76 @@
+// Same layout as in tonemap.wgsl.
+struct TonemapUniform {
+    exposure: f32,
+    gamma_exponent: f32,
+    tone_mapping: u32,
+    auto_exposure: u32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation_rate: f32,
+    middle_gray: f32,
+    time_delta: f32,
+}
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: TonemapUniform;
+@group(0) @binding(2) var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(0) @binding(3) var<storage, read_write> adapted_luminance: f32;
+
+var<workgroup> local_bins: array<atomic<u32>, 256>;
+var<workgroup> weighted_bins: array<f32, 256>;
+
+// Bin 0 collects near-black pixels; bins 1..255 split the log luminance range.
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    if (luminance < 0.0001) {
+        return 0u;
+    }
+    let t = clamp((log2(luminance) - params.min_log_luminance) / params.log_luminance_range, 0.0, 1.0);
+    return u32(t * 254.0 + 1.0);
+}
+
+// Each workgroup bins a 16x16 tile into shared memory, then adds its counts
+// to the global histogram, keeping contention on the global atomics low.
+@compute @workgroup_size(16, 16)
+fn build_histogram(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&local_bins[index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(hdr_texture);
+    if (id.x < size.x && id.y < size.y) {
+        let color = textureLoad(hdr_texture, vec2<i32>(id.xy), 0).rgb;
+        atomicAdd(&local_bins[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[index], atomicLoad(&local_bins[index]));
+}
+
+// A single workgroup averages the histogram, clears it for the next frame and
+// moves the adapted luminance towards the average.
+@compute @workgroup_size(256)
+fn average_luminance(@builtin(local_invocation_index) index: u32) {
+    let count = atomicLoad(&histogram[index]);
+    weighted_bins[index] = f32(count) * f32(index);
+    atomicStore(&histogram[index], 0u);
+    workgroupBarrier();
+
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if (index < stride) {
+            weighted_bins[index] += weighted_bins[index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if (index == 0u) {
+        // `count` is the number of black pixels here, which are left out.
+        let size = textureDimensions(hdr_texture);
+        let lit_pixels = max(f32(size.x * size.y) - f32(count), 1.0);
+        let mean_bin = max(weighted_bins[0] / lit_pixels - 1.0, 0.0);
+        let log_luminance = mean_bin / 254.0 * params.log_luminance_range + params.min_log_luminance;
+        let rate = 1.0 - exp(-params.time_delta * params.adaptation_rate);
+        adapted_luminance += (exp2(log_luminance) - adapted_luminance) * rate;
+    }
+}
@@ -0,0 +1,711 @@
+//! glTF 2.0 scene import from `.gltf` and `.glb` files.
+//!
+//! [`load_gltf`] reads the default scene's node hierarchy, meshes, PBR
+//! metallic-roughness materials, textures (decoded to RGBA8), cameras and
+//! `KHR_lights_punctual` lights into plain engine-side data. Files that
+//! require any other extension are rejected with
+//! [`GltfError::UnsupportedExtension`].
+
+use std::path::Path;
+
+use cgmath::{Matrix4, Point3, Quaternion, SquareMatrix, Vector3, Vector4};
+
+use crate::camera::Camera;
+use crate::light::{Light, LightId};
+use crate::material::Material;
+use crate::mesh::MeshData;
+use crate::renderer::Renderer;
+use crate::scene::{NodeId, Transform};
+use crate::texture::{SamplerSettings, TextureData};
+use crate::vertex::Vertex;
+
+/// Extensions the importer understands when a file lists them as required.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["KHR_lights_punctual"];
+
+/// How far back along its direction [`GltfScene::apply_light`] places a
+/// directional light, which glTF gives no position.
+const DIRECTIONAL_LIGHT_DISTANCE: f32 = 1000.0;
+
+/// Errors raised while importing a glTF file.
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file could not be read, parsed or validated, or a buffer or image
+    /// it references could not be loaded.
+    Gltf(gltf::Error),
+    /// The file requires an extension the importer does not implement.
+    UnsupportedExtension(String),
+    /// A primitive draws points or lines rather than triangles.
+    UnsupportedPrimitiveMode { mesh: String, mode: gltf::mesh::Mode },
+    /// A primitive has no `POSITION` attribute.
+    MissingPositions { mesh: String },
+    /// A vertex attribute has a different number of elements than `POSITION`.
+    AttributeCountMismatch { mesh: String, attribute: &'static str, count: usize, positions: usize },
+    /// A primitive refers to a vertex that does not exist.
+    IndexOutOfBounds { mesh: String, index: u32 },
+    /// An image uses a pixel format with no RGBA8 conversion.
+    UnsupportedImageFormat { image: usize, format: gltf::image::Format },
+}
+
+impl std::fmt::Display for GltfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GltfError::Gltf(e) => write!(f, "{}", e),
+            GltfError::UnsupportedExtension(name) => {
+                write!(f, "required extension '{}' is not supported", name)
+            }
+            GltfError::UnsupportedPrimitiveMode { mesh, mode } => {
+                write!(f, "mesh '{}' uses unsupported primitive mode {:?}", mesh, mode)
+            }
+            GltfError::MissingPositions { mesh } => {
+                write!(f, "mesh '{}' has a primitive without positions", mesh)
+            }
+            GltfError::AttributeCountMismatch { mesh, attribute, count, positions } => write!(
+                f,
+                "mesh '{}' has {} {} values for {} positions",
+                mesh, count, attribute, positions
+            ),
+            GltfError::IndexOutOfBounds { mesh, index } => {
+                write!(f, "mesh '{}': index {} is out of bounds", mesh, index)
+            }
+            GltfError::UnsupportedImageFormat { image, format } => {
+                write!(f, "image {} has unsupported format {:?}", image, format)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Gltf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(e: gltf::Error) -> Self {
+        GltfError::Gltf(e)
+    }
+}
+
+/// A node of the imported hierarchy.
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    pub name: String,
+    pub transform: Transform,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Index into [`GltfScene::meshes`].
+    pub mesh: Option<usize>,
+    /// Index into [`GltfScene::cameras`].
+    pub camera: Option<usize>,
+    /// Index into [`GltfScene::lights`].
+    pub light: Option<usize>,
+}
+
+/// One primitive of a glTF mesh.
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive {
+    /// Vertex colors are `COLOR_0` multiplied by the material's base color factor.
+    pub data: MeshData,
+    /// Index into [`GltfScene::materials`].
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// A metallic-roughness material. Texture fields index [`GltfScene::textures`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub occlusion_texture: Option<usize>,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<usize>,
+    pub double_sided: bool,
+}
+
+impl PbrMaterial {
+    /// The metallic-roughness material for meshes using this one. The base
+    /// color factor is already baked into their vertex colors, so the base
+    /// color is white.
+    ///
+    /// Only the factors carry over: the engine material has no slots for the
+    /// metallic-roughness, normal, occlusion or emissive textures.
+    pub fn to_material(&self) -> Material {
+        Material::metallic_roughness([1.0, 1.0, 1.0], self.metallic_factor, self.roughness_factor)
+    }
+}
+
+/// A texture's image, decoded to tightly packed RGBA8 rows.
+#[derive(Debug, Clone)]
+pub struct GltfTexture {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    /// The texture's sampler, wrapping `u` by `wrapS` and `v` by `wrapT`.
+    pub sampler: SamplerSettings,
+}
+
+impl GltfTexture {
+    pub fn to_texture_data(&self) -> TextureData {
+        TextureData {
+            width: self.width,
+            height: self.height,
+            rgba: self.rgba.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfProjection {
+    Perspective {
+        /// Vertical field of view in radians.
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// `None` for an infinite projection.
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfCamera {
+    pub name: String,
+    pub projection: GltfProjection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfLightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// A `KHR_lights_punctual` light. Lights shine along their node's -Z axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfLight {
+    pub name: String,
+    pub kind: GltfLightKind,
+    pub color: [f32; 3],
+    /// Candela for point and spot lights, lux for directional lights.
+    pub intensity: f32,
+    pub range: Option<f32>,
+}
+
+/// The contents of a glTF file's default scene.
+#[derive(Debug, Clone, Default)]
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    /// Root nodes of the default scene.
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<PbrMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub cameras: Vec<GltfCamera>,
+    pub lights: Vec<GltfLight>,
+}
+
+/// Imports a `.gltf` or `.glb` file along with the buffers and images it references.
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<GltfScene, GltfError> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).map_err(|e| GltfError::Gltf(gltf::Error::Io(e)))?;
+    import_slice(&bytes, path.parent())
+}
+
+/// Imports glTF JSON or GLB bytes. Relative URIs are resolved against `base_dir`;
+/// without one, only embedded data URIs and the GLB binary chunk are available.
+pub fn import_slice(bytes: &[u8], base_dir: Option<&Path>) -> Result<GltfScene, GltfError> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::from_slice_without_validation(bytes)?;
+    // Check before validation so unsupported files get a clear error.
+    if let Some(name) = document
+        .extensions_required()
+        .find(|name| !SUPPORTED_EXTENSIONS.contains(name))
+    {
+        return Err(GltfError::UnsupportedExtension(name.to_string()));
+    }
+    let document = gltf::Document::from_json(document.into_json())?;
+    let buffers = gltf::import_buffers(&document, base_dir, blob)?;
+    let images = gltf::import_images(&document, base_dir, &buffers)?;
+
+    let textures = document
+        .textures()
+        .map(|texture| {
+            let index = texture.source().index();
+            let image = &images[index];
+            Ok(GltfTexture {
+                name: texture.name().unwrap_or_default().to_string(),
+                width: image.width,
+                height: image.height,
+                rgba: to_rgba8(image, index)?,
+                sampler: convert_sampler(&texture.sampler()),
+            })
+        })
+        .collect::<Result<Vec<_>, GltfError>>()?;
+
+    let materials: Vec<_> = document.materials().map(|m| convert_material(&m)).collect();
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| convert_mesh(&mesh, &buffers, &materials))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let cameras = document
+        .cameras()
+        .map(|camera| GltfCamera {
+            name: camera.name().unwrap_or_default().to_string(),
+            projection: match camera.projection() {
+                gltf::camera::Projection::Perspective(p) => GltfProjection::Perspective {
+                    yfov: p.yfov(),
+                    aspect_ratio: p.aspect_ratio(),
+                    znear: p.znear(),
+                    zfar: p.zfar(),
+                },
+                gltf::camera::Projection::Orthographic(o) => GltfProjection::Orthographic {
+                    xmag: o.xmag(),
+                    ymag: o.ymag(),
+                    znear: o.znear(),
+                    zfar: o.zfar(),
+                },
+            },
+        })
+        .collect();
+
+    let lights = document
+        .lights()
+        .into_iter()
+        .flatten()
+        .map(|light| GltfLight {
+            name: light.name().unwrap_or_default().to_string(),
+            kind: match light.kind() {
+                gltf::khr_lights_punctual::Kind::Directional => GltfLightKind::Directional,
+                gltf::khr_lights_punctual::Kind::Point => GltfLightKind::Point,
+                gltf::khr_lights_punctual::Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => GltfLightKind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                },
+            },
+            color: light.color(),
+            intensity: light.intensity(),
+            range: light.range(),
+        })
+        .collect();
+
+    let mut nodes: Vec<_> = document
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            GltfNode {
+                name: node.name().unwrap_or_default().to_string(),
+                transform: Transform {
+                    translation: Vector3::from(translation),
+                    rotation: Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]),
+                    scale: Vector3::from(scale),
+                },
+                parent: None,
+                children: node.children().map(|child| child.index()).collect(),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                camera: node.camera().map(|camera| camera.index()),
+                light: node.light().map(|light| light.index()),
+            }
+        })
+        .collect();
+    for index in 0..nodes.len() {
+        for child in nodes[index].children.clone() {
+            nodes[child].parent = Some(index);
+        }
+    }
+
+    let roots = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+        None => (0..nodes.len()).filter(|&i| nodes[i].parent.is_none()).collect(),
+    };
+
+    Ok(GltfScene {
+        nodes,
+        roots,
+        meshes,
+        materials,
+        textures,
+        cameras,
+        lights,
+    })
+}
+
+fn convert_sampler(sampler: &gltf::texture::Sampler) -> SamplerSettings {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+    let defaults = SamplerSettings::default();
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        Some(MagFilter::Linear) => wgpu::FilterMode::Linear,
+        None => defaults.mag_filter,
+    };
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::Linear | MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::NearestMipmapLinear) => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear),
+        Some(MinFilter::LinearMipmapLinear) => (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear),
+        None => (defaults.min_filter, defaults.mipmap_filter),
+    };
+    SamplerSettings {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+        ..defaults
+    }
+}
+
+fn convert_material(material: &gltf::Material) -> PbrMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    PbrMaterial {
+        name: material.name().unwrap_or_default().to_string(),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .map(|info| info.texture().index()),
+        normal_texture: material.normal_texture().map(|info| info.texture().index()),
+        occlusion_texture: material.occlusion_texture().map(|info| info.texture().index()),
+        emissive_factor: material.emissive_factor(),
+        emissive_texture: material.emissive_texture().map(|info| info.texture().index()),
+        double_sided: material.double_sided(),
+    }
+}
+
+fn convert_mesh(
+    mesh: &gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+    materials: &[PbrMaterial],
+) -> Result<GltfMesh, GltfError> {
+    let name = mesh.name().unwrap_or_default().to_string();
+    let primitives = mesh
+        .primitives()
+        .map(|primitive| {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| GltfError::MissingPositions { mesh: name.clone() })?
+                .collect();
+
+            let material = primitive.material().index();
+            let factor = material.map_or([1.0; 4], |m| materials[m].base_color_factor);
+            let colors: Vec<[f32; 3]> = match reader.read_colors(0) {
+                Some(colors) => colors.into_rgb_f32().collect(),
+                None => vec![[1.0; 3]; positions.len()],
+            };
+            let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(Iterator::collect);
+            let tex_coords: Option<Vec<[f32; 2]>> =
+                reader.read_tex_coords(0).map(|t| t.into_f32().collect());
+            let counts = [
+                ("COLOR_0", Some(colors.len())),
+                ("NORMAL", normals.as_ref().map(Vec::len)),
+                ("TEXCOORD_0", tex_coords.as_ref().map(Vec::len)),
+            ];
+            for (attribute, count) in counts {
+                match count {
+                    Some(count) if count != positions.len() => {
+                        return Err(GltfError::AttributeCountMismatch {
+                            mesh: name.clone(),
+                            attribute,
+                            count,
+                            positions: positions.len(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+
+            let vertices = positions
+                .iter()
+                .enumerate()
+                .map(|(i, &position)| Vertex {
+                    position,
+                    color: [0, 1, 2].map(|c| colors[i][c] * factor[c]),
+                    normal: normals.as_ref().map_or([0.0; 3], |n| n[i]),
+                    tex_coords: tex_coords.as_ref().map_or([0.0; 2], |t| t[i]),
+                })
+                .collect();
+
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            if let Some(&index) = indices.iter().find(|&&i| i as usize >= positions.len()) {
+                return Err(GltfError::IndexOutOfBounds { mesh: name.clone(), index });
+            }
+            let indices = triangle_list(indices, primitive.mode()).ok_or_else(|| {
+                GltfError::UnsupportedPrimitiveMode {
+                    mesh: name.clone(),
+                    mode: primitive.mode(),
+                }
+            })?;
+
+            let mut data = MeshData::new(vertices, indices);
+            if normals.is_none() {
+                data.compute_normals();
+            }
+            Ok(GltfPrimitive { data, material })
+        })
+        .collect::<Result<Vec<_>, GltfError>>()?;
+
+    Ok(GltfMesh { name, primitives })
+}
+
+/// Converts strip and fan indices to a triangle list; `None` for points and lines.
+fn triangle_list(indices: Vec<u32>, mode: gltf::mesh::Mode) -> Option<Vec<u32>> {
+    use gltf::mesh::Mode;
+    match mode {
+        Mode::Triangles => Some(indices),
+        Mode::TriangleStrip => Some(
+            (0..indices.len().saturating_sub(2))
+                .flat_map(|i| {
+                    // Every other triangle flips to keep a consistent winding.
+                    if i % 2 == 0 {
+                        [indices[i], indices[i + 1], indices[i + 2]]
+                    } else {
+                        [indices[i + 1], indices[i], indices[i + 2]]
+                    }
+                })
+                .collect(),
+        ),
+        Mode::TriangleFan => Some(
+            (1..indices.len().saturating_sub(1))
+                .flat_map(|i| [indices[0], indices[i], indices[i + 1]])
+                .collect(),
+        ),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+fn to_rgba8(image: &gltf::image::Data, index: usize) -> Result<Vec<u8>, GltfError> {
+    use gltf::image::Format;
+    let pixels = &image.pixels;
+    let rgba = match image.format {
+        Format::R8 => pixels.iter().flat_map(|&r| [r, r, r, 255]).collect(),
+        Format::R8G8 => pixels.chunks_exact(2).flat_map(|p| [p[0], p[1], 0, 255]).collect(),
+        Format::R8G8B8 => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        Format::R8G8B8A8 => pixels.clone(),
+        // 16-bit channels are little endian; keep the high byte.
+        Format::R16G16B16A16 => pixels.chunks_exact(2).map(|c| c[1]).collect(),
+        Format::R16G16B16 => pixels
+            .chunks_exact(6)
+            .flat_map(|p| [p[1], p[3], p[5], 255])
+            .collect(),
+        format => return Err(GltfError::UnsupportedImageFormat { image: index, format }),
+    };
+    Ok(rgba)
+}
+
+impl GltfScene {
+    /// The node's world matrix, composed from its ancestors' local transforms.
+    pub fn world_matrix(&self, node: usize) -> Matrix4<f32> {
+        let mut matrix = Matrix4::identity();
+        let mut current = Some(node);
+        while let Some(index) = current {
+            matrix = self.nodes[index].transform.matrix() * matrix;
+            current = self.nodes[index].parent;
+        }
+        matrix
+    }
+
+    /// Uploads every mesh, material and texture and adds the default scene's
+    /// hierarchy under `parent`. Returns the engine node created for each glTF
+    /// node, indexed like [`GltfScene::nodes`]; nodes outside the default
+    /// scene get `None`.
+    ///
+    /// Meshes with several primitives get one child node per primitive.
+    ///
+    /// Only base color textures are drawn. Metallic-roughness, normal,
+    /// occlusion and emissive textures are uploaded like the rest but not
+    /// bound to any material: surfaces use the metallic and roughness factors
+    /// alone, with the mesh's vertex normals and no occlusion or emission.
+    pub fn add_to_scene(&self, renderer: &mut Renderer, parent: Option<NodeId>) -> Vec<Option<NodeId>> {
+        let texture_ids: Vec<_> = self
+            .textures
+            .iter()
+            .map(|texture| renderer.add_texture(&texture.to_texture_data(), texture.sampler))
+            .collect();
+        let material_ids: Vec<_> = self
+            .materials
+            .iter()
+            .map(|material| {
+                renderer.add_material(Material {
+                    texture: material.base_color_texture.map(|t| texture_ids[t]),
+                    ..material.to_material()
+                })
+            })
+            .collect();
+        let mesh_ids: Vec<Vec<_>> = self
+            .meshes
+            .iter()
+            .map(|mesh| {
+                mesh.primitives
+                    .iter()
+                    .map(|p| {
+                        let id = renderer.add_mesh(&p.data);
+                        renderer.set_mesh_material(id, p.material.map(|m| material_ids[m]));
+                        id
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut ids = vec![None; self.nodes.len()];
+        let mut stack: Vec<_> = self.roots.iter().rev().map(|&root| (root, parent)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let scene = renderer.scene_mut();
+            let id = scene.add_node(parent, node.transform, None);
+            scene.node_mut(id).unwrap().name = node.name.clone();
+            if let Some(mesh) = node.mesh {
+                match mesh_ids[mesh].as_slice() {
+                    [single] => scene.node_mut(id).unwrap().mesh = Some(*single),
+                    primitives => {
+                        for &primitive in primitives {
+                            scene.add_node(Some(id), Transform::default(), Some(primitive));
+                        }
+                    }
+                }
+            }
+            ids[index] = Some(id);
+            stack.extend(node.children.iter().rev().map(|&child| (child, Some(id))));
+        }
+        ids
+    }
+
+    /// Nodes that carry a camera, in node order.
+    pub fn camera_nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(|&i| self.nodes[i].camera.is_some())
+    }
+
+    /// Builds an engine camera from the camera attached to `node`, placed at
+    /// the node's world position and looking down its -Z axis.
+    pub fn camera(&self, node: usize, width: u32, height: u32) -> Option<Camera> {
+        let gltf_camera = &self.cameras[self.nodes[node].camera?];
+        let world = self.world_matrix(node);
+        let position = world * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let forward = world * Vector4::new(0.0, 0.0, -1.0, 0.0);
+
+        let mut camera = Camera::new(width, height);
+        camera.look_to(
+            Point3::new(position.x, position.y, position.z),
+            Vector3::new(forward.x, forward.y, forward.z),
+        );
+        match gltf_camera.projection {
+            GltfProjection::Perspective { yfov, znear, zfar: Some(zfar), .. } => {
+                camera.set_perspective(yfov.to_degrees(), znear, zfar);
+            }
+            GltfProjection::Perspective { yfov, znear, zfar: None, .. } => {
+                camera.set_reverse_z_infinite(yfov.to_degrees(), znear);
+            }
+            GltfProjection::Orthographic { ymag, znear, zfar, .. } => {
+                camera.set_orthographic(ymag * 2.0, znear, zfar);
+            }
+        }
+        Some(camera)
+    }
+
+    /// Nodes that carry a light, in node order.
+    pub fn light_nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(|&i| self.nodes[i].light.is_some())
+    }
+
+    /// Builds a dynamic light from the light attached to `node`, placed at
+    /// the node's world position and shining down its -Z axis, with the
+    /// light's intensity, range and cone angles.
+    pub fn light(&self, node: usize) -> Option<Light> {
+        let light = &self.lights[self.nodes[node].light?];
+        let world = self.world_matrix(node);
+        let position = world * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let position = Point3::new(position.x, position.y, position.z);
+        let forward = world * Vector4::new(0.0, 0.0, -1.0, 0.0);
+        let direction = Vector3::new(forward.x, forward.y, forward.z);
+        Some(match light.kind {
+            GltfLightKind::Directional => Light::directional(direction, light.color, light.intensity),
+            GltfLightKind::Point => Light::point(position, light.color, light.intensity, light.range),
+            GltfLightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => Light::spot(
+                position,
+                direction,
+                inner_cone_angle,
+                outer_cone_angle,
+                light.color,
+                light.intensity,
+                light.range,
+            ),
+        })
+    }
+
+    /// Adds every light in the scene to `renderer` as a dynamic light, in
+    /// node order.
+    pub fn add_lights(&self, renderer: &mut Renderer) -> Vec<LightId> {
+        self.light_nodes()
+            .filter_map(|node| self.light(node))
+            .map(|light| renderer.add_light(light))
+            .collect()
+    }
+
+    /// Makes the light attached to `node` the renderer's scene light, the one
+    /// that casts shadows. Only its color carries over; use
+    /// [`GltfScene::add_lights`] for intensity, range and cone angles.
+    ///
+    /// Point and spot lights are placed at the node; directional lights are
+    /// placed far back along their direction. Returns `false` if the node has
+    /// no light.
+    pub fn apply_light(&self, node: usize, renderer: &mut Renderer) -> bool {
+        let Some(light) = self.nodes[node].light.map(|light| &self.lights[light]) else {
+            return false;
+        };
+        let world = self.world_matrix(node);
+        let position = match light.kind {
+            GltfLightKind::Directional => {
+                let forward = world * Vector4::new(0.0, 0.0, -1.0, 0.0);
+                Vector3::new(forward.x, forward.y, forward.z) * -DIRECTIONAL_LIGHT_DISTANCE
+            }
+            GltfLightKind::Point | GltfLightKind::Spot { .. } => {
+                let position = world * Vector4::new(0.0, 0.0, 0.0, 1.0);
+                Vector3::new(position.x, position.y, position.z)
+            }
+        };
+        renderer.set_light(position.into(), light.color);
+        true
+    }
+}
@@ -0,0 +1,100 @@
+//! Reloading `shader.wgsl` from disk while the renderer runs.
+//!
+//! A [`ShaderWatcher`] polls a file's modification time. When it changes,
+//! the new source is parsed and validated with naga before wgpu sees it, so
+//! syntax and type errors are reported with source locations and the
+//! running pipeline is left untouched.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Errors raised while reloading a shader.
+#[derive(Debug)]
+pub enum ShaderError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: std::io::Error },
+    /// naga rejected the WGSL. The message quotes the offending source.
+    Compile(String),
+    /// wgpu rejected the shader module or pipeline, e.g. because its
+    /// bindings do not match the renderer's bind group layouts.
+    Pipeline(String),
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            ShaderError::Compile(message) => write!(f, "shader failed to compile:\n{}", message),
+            ShaderError::Pipeline(message) => write!(f, "failed to build pipeline: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShaderError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Parses and validates WGSL, labelling errors with `path`.
+pub fn validate(source: &str, path: &Path) -> Result<(), ShaderError> {
+    let label = path.display().to_string();
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| ShaderError::Compile(e.emit_to_string_with_path(source, &label)))?;
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+        .validate(&module)
+        .map_err(|e| ShaderError::Compile(e.emit_to_string_with_path(source, &label)))?;
+    Ok(())
+}
+
+/// A shader file and the modification time it was last read at.
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    /// Set once a missing file was reported, until it reappears.
+    missing: bool,
+}
+
+impl ShaderWatcher {
+    /// Watches `path`. The first [`ShaderWatcher::poll`] always reads it.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            modified: None,
+            missing: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the file's source if it changed since the last poll.
+    ///
+    /// A missing file is reported once rather than on every poll, so an
+    /// editor briefly replacing it does not print an error every frame.
+    pub fn poll(&mut self) -> Result<Option<String>, ShaderError> {
+        let io_error = |source| ShaderError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        let modified = match std::fs::metadata(&self.path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(_) if self.missing => return Ok(None),
+            Err(e) => {
+                self.missing = true;
+                return Err(io_error(e));
+            }
+        };
+        self.missing = false;
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+        self.modified = Some(modified);
+        std::fs::read_to_string(&self.path).map(Some).map_err(io_error)
+    }
+}
@@ -0,0 +1,227 @@
+// Precomputes image-based lighting from an equirectangular environment.
+// Every entry point writes one texel per invocation; cubemaps are written
+// through 2D array views with one layer per face. Each pass binds only the
+// resources its entry point uses.
+
+struct PrefilterUniform {
+    roughness: f32,
+}
+
+const PI: f32 = 3.14159265359;
+
+@group(0) @binding(0) var equirect: texture_2d<f32>;
+@group(0) @binding(1) var cube_output: texture_storage_2d_array<rgba16float, write>;
+@group(0) @binding(2) var previous_level: texture_cube<f32>;
+@group(0) @binding(3) var environment: texture_cube<f32>;
+@group(0) @binding(4) var environment_sampler: sampler;
+@group(0) @binding(5) var<uniform> prefilter: PrefilterUniform;
+@group(0) @binding(6) var lut_output: texture_storage_2d<rgba16float, write>;
+
+// Direction through texel `id` of a `size`-wide cube face, following the
+// cubemap face order +X, -X, +Y, -Y, +Z, -Z.
+fn cube_direction(id: vec3<u32>, size: u32) -> vec3<f32> {
+    let uv = (vec2<f32>(id.xy) + 0.5) / f32(size) * 2.0 - 1.0;
+    var direction: vec3<f32>;
+    switch id.z {
+        case 0u: { direction = vec3<f32>(1.0, -uv.y, -uv.x); }
+        case 1u: { direction = vec3<f32>(-1.0, -uv.y, uv.x); }
+        case 2u: { direction = vec3<f32>(uv.x, 1.0, uv.y); }
+        case 3u: { direction = vec3<f32>(uv.x, -1.0, -uv.y); }
+        case 4u: { direction = vec3<f32>(uv.x, -uv.y, 1.0); }
+        default: { direction = vec3<f32>(-uv.x, -uv.y, -1.0); }
+    }
+    return normalize(direction);
+}
+
+fn load_equirect(texel: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+    let wrapped = vec2<i32>((texel.x + size.x) % size.x, clamp(texel.y, 0, size.y - 1));
+    return textureLoad(equirect, wrapped, 0).rgb;
+}
+
+// The source is a 32-bit float texture, which is not filterable everywhere,
+// so it is filtered by hand: wrapping around the horizon, clamped at the poles.
+@compute @workgroup_size(8, 8, 1)
+fn equirect_to_cube(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    let direction = cube_direction(id, size);
+    let uv = vec2<f32>(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+
+    let source_size = vec2<i32>(textureDimensions(equirect));
+    let position = uv * vec2<f32>(source_size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let t = fract(position);
+    let top = mix(load_equirect(base, source_size), load_equirect(base + vec2<i32>(1, 0), source_size), t.x);
+    let bottom = mix(
+        load_equirect(base + vec2<i32>(0, 1), source_size),
+        load_equirect(base + vec2<i32>(1, 1), source_size),
+        t.x,
+    );
+    textureStore(cube_output, vec2<i32>(id.xy), i32(id.z), vec4<f32>(mix(top, bottom, t.y), 1.0));
+}
+
+// Averages each 2x2 block of the previous mip level into the next: each
+// output texel's center is the corner its four source texels share, so one
+// bilinear sample weighs them equally. The previous level is bound as a cube,
+// as some backends store every six-layer texture as one.
+@compute @workgroup_size(8, 8, 1)
+fn downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    let color = textureSampleLevel(previous_level, environment_sampler, cube_direction(id, size), 0.0);
+    textureStore(cube_output, vec2<i32>(id.xy), i32(id.z), color);
+}
+
+fn radical_inverse(bits_in: u32) -> f32 {
+    var bits = (bits_in << 16u) | (bits_in >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return f32(bits) * 2.3283064365386963e-10;
+}
+
+// Low-discrepancy point `i` of `count` in the unit square.
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), radical_inverse(i));
+}
+
+// Rotates `local`, given around +Z, into the frame around `normal`.
+fn to_world(local: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var up = vec3<f32>(0.0, 0.0, 1.0);
+    if (abs(normal.z) > 0.999) {
+        up = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+    return tangent * local.x + bitangent * local.y + normal * local.z;
+}
+
+// Half vector for sample `xi` of the GGX distribution around `normal`.
+fn importance_sample_ggx(xi: vec2<f32>, normal: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return to_world(vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), normal);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Mip level of the environment whose texels cover about the solid angle of a
+// sample drawn with probability density `pdf` out of `count`. Sampling there
+// instead of the top level avoids bright pixels turning into speckles.
+fn sample_level(pdf: f32, count: u32) -> f32 {
+    let size = f32(textureDimensions(environment).x);
+    let sample_angle = 1.0 / (f32(count) * pdf + 0.0001);
+    let texel_angle = 4.0 * PI / (6.0 * size * size);
+    return max(0.5 * log2(sample_angle / texel_angle) + 1.0, 0.0);
+}
+
+const IRRADIANCE_SAMPLES: u32 = 256u;
+
+// Cosine-weighted mean of the incoming radiance around each direction, which
+// times the albedo is the light a matte surface facing it reflects.
+@compute @workgroup_size(8, 8, 1)
+fn irradiance(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    let normal = cube_direction(id, size);
+    var sum = vec3<f32>(0.0);
+    for (var i = 0u; i < IRRADIANCE_SAMPLES; i++) {
+        let xi = hammersley(i, IRRADIANCE_SAMPLES);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt(1.0 - xi.y);
+        let sin_theta = sqrt(xi.y);
+        let direction = to_world(vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), normal);
+        let level = sample_level(cos_theta / PI, IRRADIANCE_SAMPLES);
+        sum += textureSampleLevel(environment, environment_sampler, direction, level).rgb;
+    }
+    textureStore(cube_output, vec2<i32>(id.xy), i32(id.z), vec4<f32>(sum / f32(IRRADIANCE_SAMPLES), 1.0));
+}
+
+const PREFILTER_SAMPLES: u32 = 128u;
+
+// Radiance reflected towards each direction by a surface of the level's
+// roughness, assuming the view and normal match the reflected direction.
+@compute @workgroup_size(8, 8, 1)
+fn prefilter_specular(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    let normal = cube_direction(id, size);
+    if (prefilter.roughness == 0.0) {
+        // A mirror: the environment at the level matching this one's size.
+        let level = log2(f32(textureDimensions(environment).x) / f32(size));
+        let color = textureSampleLevel(environment, environment_sampler, normal, level);
+        textureStore(cube_output, vec2<i32>(id.xy), i32(id.z), color);
+        return;
+    }
+
+    var sum = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < PREFILTER_SAMPLES; i++) {
+        let halfway = importance_sample_ggx(hammersley(i, PREFILTER_SAMPLES), normal, prefilter.roughness);
+        let light_dir = normalize(2.0 * dot(normal, halfway) * halfway - normal);
+        let n_dot_l = dot(normal, light_dir);
+        if (n_dot_l > 0.0) {
+            // With view = normal, the pdf of the reflected direction reduces to D / 4.
+            let n_dot_h = max(dot(normal, halfway), 0.0);
+            let level = sample_level(distribution_ggx(n_dot_h, prefilter.roughness) / 4.0, PREFILTER_SAMPLES);
+            sum += textureSampleLevel(environment, environment_sampler, light_dir, level).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    textureStore(cube_output, vec2<i32>(id.xy), i32(id.z), vec4<f32>(sum / max(weight, 0.0001), 1.0));
+}
+
+const BRDF_SAMPLES: u32 = 256u;
+
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+// Scale and bias to the base reflectance of the split-sum approximation,
+// indexed by the cosine of the view angle and the roughness.
+@compute @workgroup_size(8, 8, 1)
+fn integrate_brdf(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(lut_output);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let n_dot_v = (f32(id.x) + 0.5) / f32(size.x);
+    let roughness = (f32(id.y) + 0.5) / f32(size.y);
+    let view_dir = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let normal = vec3<f32>(0.0, 0.0, 1.0);
+
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < BRDF_SAMPLES; i++) {
+        let halfway = importance_sample_ggx(hammersley(i, BRDF_SAMPLES), normal, roughness);
+        let light_dir = normalize(2.0 * dot(view_dir, halfway) * halfway - view_dir);
+        let n_dot_l = max(light_dir.z, 0.0);
+        if (n_dot_l > 0.0) {
+            let n_dot_h = max(halfway.z, 0.0);
+            let v_dot_h = max(dot(view_dir, halfway), 0.0);
+            let visibility = geometry_smith_ibl(n_dot_v, n_dot_l, roughness) * v_dot_h / (n_dot_h * n_dot_v);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+    let result = vec2<f32>(scale, bias) / f32(BRDF_SAMPLES);
+    textureStore(lut_output, vec2<i32>(id.xy), vec4<f32>(result, 0.0, 1.0));
+}
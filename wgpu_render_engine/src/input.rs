@@ -0,0 +1,195 @@
+//! Named actions bound to keys and mouse buttons.
+//!
+//! The renderer looks up every key and button event in an [`ActionMap`] and
+//! hands the resulting [`Action`]s to the camera controller, so controls can
+//! be remapped without touching code. Bindings load from TOML:
+//!
+//! ```toml
+//! move_forward = ["Z", "Up"]
+//! move_left = ["Q", "Left"]
+//! rotate = [{ mouse = "Right" }]
+//! ```
+//!
+//! Keys use winit's [`VirtualKeyCode`] names. Actions missing from the file
+//! keep their default bindings.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+/// Something the user can do, independent of the input that triggers it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    /// Held to turn the camera, or to orbit it around its target.
+    Rotate,
+    /// Held to pan the camera within the view plane.
+    Pan,
+    /// Toggles mouse capture, which turns the camera without holding a button.
+    ToggleCapture,
+    /// Releases mouse capture; does nothing while not captured.
+    ReleaseCapture,
+    /// Switches between fly and orbit mode.
+    ToggleCameraMode,
+    /// Points the camera at the whole scene.
+    FrameScene,
+    /// Saves the next frame as a PNG.
+    Screenshot,
+}
+
+/// A key or mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse { mouse: MouseButton },
+}
+
+impl From<VirtualKeyCode> for Binding {
+    fn from(key: VirtualKeyCode) -> Self {
+        Binding::Key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::Mouse { mouse: button }
+    }
+}
+
+/// Errors raised while loading an [`ActionMap`].
+#[derive(Debug)]
+pub enum ActionMapError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: std::io::Error },
+    /// The file is not valid TOML, or names an unknown action or key.
+    Parse(toml::de::Error),
+}
+
+impl std::fmt::Display for ActionMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionMapError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            ActionMapError::Parse(e) => write!(f, "invalid input bindings: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ActionMapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ActionMapError::Io { source, .. } => Some(source),
+            ActionMapError::Parse(e) => Some(e),
+        }
+    }
+}
+
+/// The bindings of every action. An input may trigger several actions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ActionMap {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for ActionMap {
+    /// WASD or the arrow keys to move, Space and left Shift to rise and sink,
+    /// left drag to rotate, middle drag to pan, C to capture the mouse, Tab to
+    /// switch camera modes, F to frame the scene and F12 for screenshots.
+    fn default() -> Self {
+        use VirtualKeyCode::*;
+        let bindings = [
+            (Action::MoveForward, vec![W.into(), Up.into()]),
+            (Action::MoveBackward, vec![S.into(), Down.into()]),
+            (Action::MoveLeft, vec![A.into(), Left.into()]),
+            (Action::MoveRight, vec![D.into(), Right.into()]),
+            (Action::MoveUp, vec![Space.into()]),
+            (Action::MoveDown, vec![LShift.into()]),
+            (Action::Rotate, vec![MouseButton::Left.into()]),
+            (Action::Pan, vec![MouseButton::Middle.into()]),
+            (Action::ToggleCapture, vec![C.into()]),
+            (Action::ReleaseCapture, vec![Escape.into()]),
+            (Action::ToggleCameraMode, vec![Tab.into()]),
+            (Action::FrameScene, vec![F.into()]),
+            (Action::Screenshot, vec![F12.into()]),
+        ];
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl ActionMap {
+    /// A map with no bindings at all.
+    pub fn empty() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// Loads bindings from a TOML file on top of the defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ActionMapError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| ActionMapError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&source)
+    }
+
+    /// Parses TOML bindings on top of the defaults. Each action listed
+    /// replaces all of that action's default bindings.
+    pub fn from_toml(source: &str) -> Result<Self, ActionMapError> {
+        let overrides: ActionMap = toml::from_str(source).map_err(ActionMapError::Parse)?;
+        let mut map = Self::default();
+        map.bindings.extend(overrides.bindings);
+        Ok(map)
+    }
+
+    /// Every action and its bindings as TOML, in the format [`ActionMap::load`] reads.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("bindings are always representable in TOML")
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replaces the bindings of `action`.
+    pub fn rebind(&mut self, action: Action, bindings: impl IntoIterator<Item = Binding>) {
+        self.bindings.insert(action, bindings.into_iter().collect());
+    }
+
+    /// Adds `binding` to `action`, keeping its other bindings.
+    pub fn bind(&mut self, action: Action, binding: impl Into<Binding>) {
+        let binding = binding.into();
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes `binding` from every action.
+    pub fn unbind(&mut self, binding: impl Into<Binding>) {
+        let binding = binding.into();
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// The actions `binding` triggers.
+    pub fn actions(&self, binding: impl Into<Binding>) -> impl Iterator<Item = Action> + '_ {
+        let binding = binding.into();
+        self.bindings
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+}
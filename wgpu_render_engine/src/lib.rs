@@ -0,0 +1,49 @@
+pub mod camera;
+pub mod engine;
+pub mod environment;
+pub mod gltf_import;
+pub mod hot_reload;
+pub mod input;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod obj;
+pub mod readback;
+pub mod renderer;
+pub mod scene;
+pub mod shadow;
+pub mod screenshot;
+pub mod skybox;
+mod slot;
+pub mod texture;
+pub mod tonemap;
+pub mod transform;
+pub mod uniform;
+pub mod vertex;
+
+pub use camera::{Camera, CameraController, CameraMode, OrthoView, Projection};
+pub use engine::{App, Engine};
+pub use environment::{Environment, EnvironmentData};
+pub use gltf_import::{
+    load_gltf, GltfCamera, GltfError, GltfLight, GltfLightKind, GltfMesh, GltfNode, GltfPrimitive,
+    GltfProjection, GltfScene, GltfTexture, PbrMaterial,
+};
+pub use hot_reload::{ShaderError, ShaderWatcher};
+pub use input::{Action, ActionMap, ActionMapError, Binding};
+pub use light::{Light, LightBuffer, LightId, LightKind};
+pub use material::{Grid, Material, MaterialId, Shading};
+pub use mesh::{Aabb, Mesh, MeshData, MeshId, Submesh};
+pub use obj::{load_obj, ObjError, ObjMaterial, ObjMesh, ObjModel};
+pub use readback::ReadbackError;
+pub use renderer::{Renderer, RendererError};
+pub use scene::{Node, NodeId, Scene, Transform};
+pub use screenshot::CaptureError;
+pub use skybox::{Atmosphere, ProceduralSky, Sky, Skybox};
+pub use shadow::{ShadowMap, ShadowSettings};
+pub use texture::{SamplerSettings, Texture, TextureData, TextureError, TextureId};
+pub use tonemap::{AutoExposure, ToneMapping, TonemapSettings, Tonemapper};
+pub use transform::TransformBuffer;
+pub use vertex::Vertex;
+
+#[doc(hidden)]
+pub use paste::paste as __paste;
@@ -0,0 +1,237 @@
+//! Dynamic point, spot and directional lights stored in one storage buffer.
+//!
+//! These are shaded in addition to the renderer's main light, which is the
+//! one that casts shadows.
+
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use crate::slot::{SlotKey, Slots};
+use crate::uniform::RuntimeArray;
+
+/// How a [`Light`] emits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Shines in every direction from `position`.
+    Point,
+    /// Shines from `position` along `direction` in a cone. Angles are in
+    /// radians from the cone axis; the falloff is smooth between them.
+    Spot { inner_angle: f32, outer_angle: f32 },
+    /// Shines along `direction` from infinitely far away.
+    Directional,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Ignored by directional lights.
+    pub position: Point3<f32>,
+    /// Ignored by point lights.
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which point and spot lights fade out completely. `None`
+    /// attenuates with inverse-square distance only.
+    pub range: Option<f32>,
+}
+
+impl Light {
+    pub fn point(position: Point3<f32>, color: [f32; 3], intensity: f32, range: Option<f32>) -> Self {
+        Self {
+            kind: LightKind::Point,
+            position,
+            direction: -Vector3::unit_y(),
+            color,
+            intensity,
+            range,
+        }
+    }
+
+    pub fn spot(
+        position: Point3<f32>,
+        direction: Vector3<f32>,
+        inner_angle: f32,
+        outer_angle: f32,
+        color: [f32; 3],
+        intensity: f32,
+        range: Option<f32>,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            },
+            position,
+            direction,
+            color,
+            intensity,
+            range,
+        }
+    }
+
+    pub fn directional(direction: Vector3<f32>, color: [f32; 3], intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional,
+            position: Point3::new(0.0, 0.0, 0.0),
+            direction,
+            color,
+            intensity,
+            range: None,
+        }
+    }
+
+    fn to_gpu(self) -> GpuLight {
+        let (kind, inner_cos, outer_cos) = match self.kind {
+            LightKind::Point => (LIGHT_POINT, 0.0, 0.0),
+            LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            } => (LIGHT_SPOT, inner_angle.cos(), outer_angle.cos()),
+            LightKind::Directional => (LIGHT_DIRECTIONAL, 0.0, 0.0),
+        };
+        let direction = if self.direction.magnitude2() > 0.0 {
+            self.direction.normalize()
+        } else {
+            -Vector3::unit_y()
+        };
+        GpuLight::new(
+            self.position.into(),
+            kind,
+            direction.into(),
+            self.range.unwrap_or(0.0),
+            self.color,
+            self.intensity,
+            inner_cos,
+            outer_cos,
+        )
+    }
+}
+
+/// Handle to a light added with [`Renderer::add_light`]. Once the light is
+/// removed the handle never refers to anything again, even after a new light
+/// takes its place.
+///
+/// [`Renderer::add_light`]: crate::Renderer::add_light
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightId(pub(crate) SlotKey);
+
+const LIGHT_POINT: u32 = 0;
+const LIGHT_SPOT: u32 = 1;
+const LIGHT_DIRECTIONAL: u32 = 2;
+
+crate::wgsl_struct! {
+    pub(crate) struct GpuLight {
+        position: [f32; 3],
+        kind: u32,
+        direction: [f32; 3],
+        range: f32,
+        color: [f32; 3],
+        intensity: f32,
+        inner_cos: f32,
+        outer_cos: f32,
+    }
+}
+
+crate::wgsl_struct! {
+    /// `LightArray` in `shader.wgsl` up to its lights, which are written
+    /// right after it.
+    pub(crate) struct LightArray {
+        count: u32,
+        lights: RuntimeArray<GpuLight>,
+    }
+}
+
+const HEADER_SIZE: wgpu::BufferAddress = std::mem::size_of::<LightArray>() as wgpu::BufferAddress;
+const LIGHT_SIZE: wgpu::BufferAddress = std::mem::size_of::<GpuLight>() as wgpu::BufferAddress;
+
+/// The dynamic lights and the storage buffer they are uploaded to.
+///
+/// Edits mark the set dirty; [`LightBuffer::upload`] writes the active lights
+/// contiguously and grows the buffer by doubling when needed.
+pub struct LightBuffer {
+    lights: Slots<Light>,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    dirty: bool,
+}
+
+impl LightBuffer {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            lights: Slots::new(),
+            buffer: Self::create_buffer(device, capacity),
+            capacity,
+            dirty: true,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Array Buffer"),
+            size: HEADER_SIZE + LIGHT_SIZE * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn add(&mut self, light: Light) -> LightId {
+        self.dirty = true;
+        LightId(self.lights.insert(light))
+    }
+
+    pub fn remove(&mut self, id: LightId) -> Option<Light> {
+        let light = self.lights.remove(id.0);
+        self.dirty |= light.is_some();
+        light
+    }
+
+    pub fn get(&self, id: LightId) -> Option<&Light> {
+        self.lights.get(id.0)
+    }
+
+    pub fn get_mut(&mut self, id: LightId) -> Option<&mut Light> {
+        let light = self.lights.get_mut(id.0)?;
+        self.dirty = true;
+        Some(light)
+    }
+
+    /// Iterates over every active light.
+    pub fn iter(&self) -> impl Iterator<Item = (LightId, &Light)> {
+        self.lights.iter().map(|(key, light)| (LightId(key), light))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.values().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the active lights to the GPU if anything changed. Returns `true`
+    /// if the buffer was reallocated, in which case bind groups referring to
+    /// [`LightBuffer::buffer`] must be rebuilt.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+
+        let gpu_lights: Vec<GpuLight> = self.lights.values().map(|light| light.to_gpu()).collect();
+        let reallocated = gpu_lights.len() > self.capacity;
+        if reallocated {
+            self.capacity = gpu_lights.len().next_power_of_two();
+            self.buffer = Self::create_buffer(device, self.capacity);
+        }
+        let header = LightArray::new(gpu_lights.len() as u32, RuntimeArray::default());
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&header));
+        if !gpu_lights.is_empty() {
+            queue.write_buffer(&self.buffer, HEADER_SIZE, bytemuck::cast_slice(&gpu_lights));
+        }
+        reallocated
+    }
+}
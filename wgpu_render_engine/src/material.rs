@@ -0,0 +1,226 @@
+//! Surface materials, each bound as its own bind group.
+
+use wgpu::util::DeviceExt;
+
+use crate::slot::SlotKey;
+use crate::texture::{Texture, TextureId};
+
+/// A procedural grid drawn in world space, e.g. on the demo ground.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    /// Color of the grid lines; cells use the material's base color.
+    pub line_color: [f32; 3],
+    /// Cells per world unit.
+    pub scale: f32,
+}
+
+/// The lighting model a [`Material`] is shaded with.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Shading {
+    /// Blinn-Phong, with highlights set by [`Material::specular_strength`]
+    /// and [`Material::shininess`].
+    #[default]
+    BlinnPhong,
+    /// The Cook-Torrance BRDF with GGX distribution, Smith geometry and
+    /// Schlick Fresnel terms, as used by glTF. Both parameters range from 0
+    /// to 1.
+    MetallicRoughness { metallic: f32, roughness: f32 },
+}
+
+/// How a mesh is shaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    /// Multiplied with the vertex color, or the cell color of a [`Grid`].
+    pub base_color: [f32; 3],
+    /// Sampled at the vertex UVs and multiplied with the base color.
+    pub texture: Option<TextureId>,
+    /// Scales the specular highlight of every light.
+    pub specular_strength: f32,
+    /// Blinn-Phong exponent; higher values give smaller, sharper highlights.
+    pub shininess: f32,
+    /// Replaces the vertex color with a world-space grid.
+    pub grid: Option<Grid>,
+    pub shading: Shading,
+}
+
+impl Default for Material {
+    /// Vertex colors with a full-strength highlight.
+    fn default() -> Self {
+        Self {
+            base_color: [1.0, 1.0, 1.0],
+            texture: None,
+            specular_strength: 1.0,
+            shininess: 32.0,
+            grid: None,
+            shading: Shading::BlinnPhong,
+        }
+    }
+}
+
+impl Material {
+    /// The demo ground: a green grid with a dim highlight.
+    pub fn grid_ground() -> Self {
+        Self {
+            base_color: [0.2, 0.5, 0.2],
+            texture: None,
+            specular_strength: 0.2,
+            shininess: 32.0,
+            grid: Some(Grid {
+                line_color: [0.15, 0.4, 0.15],
+                scale: 2.0,
+            }),
+            shading: Shading::BlinnPhong,
+        }
+    }
+
+    /// A physically based material with vertex colors tinted by `base_color`.
+    pub fn metallic_roughness(base_color: [f32; 3], metallic: f32, roughness: f32) -> Self {
+        Self {
+            base_color,
+            shading: Shading::MetallicRoughness { metallic, roughness },
+            ..Self::default()
+        }
+    }
+
+    fn to_gpu(self) -> MaterialUniform {
+        let grid = self.grid.unwrap_or(Grid {
+            line_color: self.base_color,
+            scale: 0.0,
+        });
+        let (shading, metallic, roughness) = match self.shading {
+            Shading::BlinnPhong => (0, 0.0, 1.0),
+            Shading::MetallicRoughness { metallic, roughness } => (1, metallic, roughness),
+        };
+        MaterialUniform::new(
+            self.base_color,
+            self.specular_strength,
+            grid.line_color,
+            self.shininess,
+            grid.scale,
+            shading,
+            metallic,
+            roughness,
+        )
+    }
+}
+
+/// Handle to a material added with [`Renderer::add_material`].
+/// Once the material is removed the handle never refers to anything again, even
+/// after a new material takes its place.
+///
+/// [`Renderer::add_material`]: crate::Renderer::add_material
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub(crate) SlotKey);
+
+crate::wgsl_struct! {
+    /// `grid_scale` is zero when the material has no grid. `shading` is 0
+    /// for Blinn-Phong and 1 for metallic-roughness.
+    pub(crate) struct MaterialUniform {
+        base_color: [f32; 3],
+        specular_strength: f32,
+        grid_line_color: [f32; 3],
+        shininess: f32,
+        grid_scale: f32,
+        shading: u32,
+        metallic: f32,
+        roughness: f32,
+    }
+}
+
+pub(crate) fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Material Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+/// A [`Material`] and the uniform buffer and bind group it is drawn with.
+///
+/// The bind group holds the material's texture, so changing
+/// [`Material::texture`] needs a new `GpuMaterial`.
+pub(crate) struct GpuMaterial {
+    material: Material,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GpuMaterial {
+    /// `texture` is the texture [`Material::texture`] refers to, or a white
+    /// one when it is `None`.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        material: Material,
+        texture: &Texture,
+    ) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: bytemuck::cast_slice(&[material.to_gpu()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Material Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(texture.sampler()),
+                },
+            ],
+        });
+        Self {
+            material,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Replaces the material and uploads it, keeping the bound texture.
+    pub fn set(&mut self, queue: &wgpu::Queue, material: Material) {
+        self.material = material;
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[material.to_gpu()]));
+    }
+}
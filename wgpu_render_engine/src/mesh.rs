@@ -0,0 +1,386 @@
+//! Indexed geometry: CPU-side [`MeshData`] and its GPU counterpart [`Mesh`].
+
+use std::ops::Range;
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Transform as _, Vector3, Zero};
+use wgpu::util::DeviceExt;
+
+use crate::material::MaterialId;
+use crate::slot::SlotKey;
+use crate::vertex::Vertex;
+
+/// A range of a mesh's index buffer drawn with one draw call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submesh {
+    /// Indices into the mesh's index buffer.
+    pub indices: Range<u32>,
+    /// Added to every index before fetching a vertex.
+    pub base_vertex: i32,
+}
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    /// The smallest box containing every point, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self { min: first, max: first }, |bounds, point| Self {
+            min: Point3::new(bounds.min.x.min(point.x), bounds.min.y.min(point.y), bounds.min.z.min(point.z)),
+            max: Point3::new(bounds.max.x.max(point.x), bounds.max.y.max(point.y), bounds.max.z.max(point.z)),
+        }))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::from_points([self.min, self.max, other.min, other.max]).unwrap()
+    }
+
+    /// The box containing all eight corners after transforming them by `matrix`.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let corners = (0..8).map(|i| {
+            Point3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            )
+        });
+        Self::from_points(corners.map(|corner| matrix.transform_point(corner))).unwrap()
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    /// Radius of the sphere around [`Aabb::center`] that contains the box.
+    pub fn radius(&self) -> f32 {
+        (self.max - self.min).magnitude() * 0.5
+    }
+}
+
+/// Geometry on the CPU, ready to be uploaded with [`Renderer::add_mesh`].
+///
+/// [`Renderer::add_mesh`]: crate::Renderer::add_mesh
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub submeshes: Vec<Submesh>,
+}
+
+impl MeshData {
+    /// Creates a mesh with a single submesh covering every index.
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        let submeshes = vec![Submesh {
+            indices: 0..indices.len() as u32,
+            base_vertex: 0,
+        }];
+        Self {
+            vertices,
+            indices,
+            submeshes,
+        }
+    }
+
+    /// Appends `other` as new submeshes that share this mesh's buffers.
+    pub fn append(&mut self, other: &MeshData) {
+        let base_vertex = self.vertices.len() as i32;
+        let first_index = self.indices.len() as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend_from_slice(&other.indices);
+        self.submeshes.extend(other.submeshes.iter().map(|submesh| Submesh {
+            indices: submesh.indices.start + first_index..submesh.indices.end + first_index,
+            base_vertex: submesh.base_vertex + base_vertex,
+        }));
+    }
+
+    /// Bounds of every vertex position, or `None` for an empty mesh.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|vertex| Point3::from(vertex.position)))
+    }
+
+    /// Replaces every vertex normal with the area-weighted average of the
+    /// normals of the triangles that use it.
+    pub fn compute_normals(&mut self) {
+        let mut sums = vec![Vector3::zero(); self.vertices.len()];
+        for submesh in &self.submeshes {
+            let indices = &self.indices[submesh.indices.start as usize..submesh.indices.end as usize];
+            for triangle in indices.chunks_exact(3) {
+                let corners = [0, 1, 2].map(|i| (triangle[i] as i32 + submesh.base_vertex) as usize);
+                let [a, b, c] = corners.map(|i| Vector3::from(self.vertices[i].position));
+                // Unnormalized, so larger triangles weigh more.
+                let normal = (b - a).cross(c - a);
+                for i in corners {
+                    sums[i] += normal;
+                }
+            }
+        }
+        for (vertex, sum) in self.vertices.iter_mut().zip(sums) {
+            let normal = if sum.magnitude2() > 0.0 { sum.normalize() } else { Vector3::unit_y() };
+            vertex.normal = normal.into();
+        }
+    }
+
+    /// The engine's demo pyramid: four colored sides and a square base,
+    /// one unit wide and centered on the origin. Every face spans the whole
+    /// texture.
+    pub fn pyramid() -> Self {
+        let vertices = vec![
+            // Front face of pyramid
+            Vertex {
+                position: [0.0, 1.0, 0.0],      // Top vertex
+                color: [1.0, 0.0, 0.0],         // Red
+                normal: [0.0, 0.5, 1.0],
+                tex_coords: [0.5, 0.0],
+            },
+            Vertex {
+                position: [-0.5, -0.5, 0.5],    // Bottom left
+                color: [0.0, 1.0, 0.0],         // Green
+                normal: [0.0, 0.5, 1.0],
+                tex_coords: [0.0, 1.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, 0.5],     // Bottom right
+                color: [0.0, 0.0, 1.0],         // Blue
+                normal: [0.0, 0.5, 1.0],
+                tex_coords: [1.0, 1.0],
+            },
+
+            // Right face of pyramid
+            Vertex {
+                position: [0.0, 1.0, 0.0],      // Top vertex
+                color: [1.0, 1.0, 0.0],         // Yellow
+                normal: [1.0, 0.5, 0.0],
+                tex_coords: [0.5, 0.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, 0.5],     // Bottom front
+                color: [1.0, 0.0, 1.0],         // Magenta
+                normal: [1.0, 0.5, 0.0],
+                tex_coords: [0.0, 1.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, -0.5],    // Bottom back
+                color: [0.0, 1.0, 1.0],         // Cyan
+                normal: [1.0, 0.5, 0.0],
+                tex_coords: [1.0, 1.0],
+            },
+
+            // Back face of pyramid
+            Vertex {
+                position: [0.0, 1.0, 0.0],      // Top vertex
+                color: [0.5, 0.5, 0.5],         // Gray
+                normal: [0.0, 0.5, -1.0],
+                tex_coords: [0.5, 0.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, -0.5],    // Bottom right
+                color: [0.7, 0.2, 0.3],         // Dark Pink
+                normal: [0.0, 0.5, -1.0],
+                tex_coords: [0.0, 1.0],
+            },
+            Vertex {
+                position: [-0.5, -0.5, -0.5],   // Bottom left
+                color: [0.2, 0.7, 0.3],         // Dark Green
+                normal: [0.0, 0.5, -1.0],
+                tex_coords: [1.0, 1.0],
+            },
+
+            // Left face of pyramid
+            Vertex {
+                position: [0.0, 1.0, 0.0],      // Top vertex
+                color: [0.3, 0.7, 0.5],         // Teal
+                normal: [-1.0, 0.5, 0.0],
+                tex_coords: [0.5, 0.0],
+            },
+            Vertex {
+                position: [-0.5, -0.5, -0.5],   // Bottom back
+                color: [0.8, 0.6, 0.2],         // Brown
+                normal: [-1.0, 0.5, 0.0],
+                tex_coords: [0.0, 1.0],
+            },
+            Vertex {
+                position: [-0.5, -0.5, 0.5],    // Bottom front
+                color: [0.4, 0.4, 0.8],         // Indigo
+                normal: [-1.0, 0.5, 0.0],
+                tex_coords: [1.0, 1.0],
+            },
+
+            // Bottom face of pyramid
+            Vertex {
+                position: [-0.5, -0.5, 0.5],    // Front left
+                color: [0.5, 0.2, 0.7],         // Purple
+                normal: [0.0, -1.0, 0.0],
+                tex_coords: [0.0, 0.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, 0.5],     // Front right
+                color: [0.2, 0.5, 0.7],         // Blue-Green
+                normal: [0.0, -1.0, 0.0],
+                tex_coords: [1.0, 0.0],
+            },
+            Vertex {
+                position: [0.5, -0.5, -0.5],    // Back right
+                color: [0.7, 0.5, 0.2],         // Orange
+                normal: [0.0, -1.0, 0.0],
+                tex_coords: [1.0, 1.0],
+            },
+            Vertex {
+                position: [-0.5, -0.5, -0.5],   // Back left
+                color: [0.3, 0.6, 0.1],         // Lime Green
+                normal: [0.0, -1.0, 0.0],
+                tex_coords: [0.0, 1.0],
+            },
+        ];
+
+        let indices = vec![
+            0, 1, 2, // Front
+            3, 4, 5, // Right
+            6, 7, 8, // Back
+            9, 10, 11, // Left
+            12, 13, 14, 14, 15, 12, // Bottom
+        ];
+
+        Self::new(vertices, indices)
+    }
+
+    /// The demo ground: a 40x40 plane at `y = -1.5` made of three strips of
+    /// slightly different greens, with one texture repeat per world unit. Draw
+    /// it with [`Material::grid_ground`] for the grid pattern.
+    ///
+    /// [`Material::grid_ground`]: crate::Material::grid_ground
+    pub fn ground_plane() -> Self {
+        let up = [0.0, 1.0, 0.0];
+        let vertices = vec![
+            // Front section
+            Vertex { position: [-20.0, -1.5, -20.0], color: [0.2, 0.5, 0.2], normal: up, tex_coords: [-20.0, -20.0] },    // Base green
+            Vertex { position: [20.0, -1.5, -20.0], color: [0.22, 0.55, 0.22], normal: up, tex_coords: [20.0, -20.0] },  // Slightly lighter green
+            Vertex { position: [20.0, -1.5, -10.0], color: [0.25, 0.6, 0.25], normal: up, tex_coords: [20.0, -10.0] },   // Varied green
+            Vertex { position: [-20.0, -1.5, -10.0], color: [0.27, 0.65, 0.27], normal: up, tex_coords: [-20.0, -10.0] }, // Another green variation
+
+            // Middle section
+            Vertex { position: [-20.0, -1.5, -10.0], color: [0.25, 0.6, 0.25], normal: up, tex_coords: [-20.0, -10.0] },  // Varied green
+            Vertex { position: [20.0, -1.5, -10.0], color: [0.25, 0.6, 0.25], normal: up, tex_coords: [20.0, -10.0] },   // Varied green
+            Vertex { position: [20.0, -1.5, 10.0], color: [0.3, 0.65, 0.3], normal: up, tex_coords: [20.0, 10.0] },     // Slightly brighter green
+            Vertex { position: [-20.0, -1.5, 10.0], color: [0.32, 0.7, 0.32], normal: up, tex_coords: [-20.0, 10.0] },   // Brighter green variation
+
+            // Back section
+            Vertex { position: [-20.0, -1.5, 10.0], color: [0.3, 0.65, 0.3], normal: up, tex_coords: [-20.0, 10.0] },    // Slightly brighter green
+            Vertex { position: [20.0, -1.5, 10.0], color: [0.3, 0.65, 0.3], normal: up, tex_coords: [20.0, 10.0] },     // Slightly brighter green
+            Vertex { position: [20.0, -1.5, 20.0], color: [0.2, 0.55, 0.2], normal: up, tex_coords: [20.0, 20.0] },     // Base green variation
+            Vertex { position: [-20.0, -1.5, 20.0], color: [0.2, 0.5, 0.2], normal: up, tex_coords: [-20.0, 20.0] },     // Base green
+        ];
+
+        let indices = (0..3)
+            .flat_map(|section| [0, 1, 2, 0, 2, 3].map(|i| section * 4 + i))
+            .collect();
+
+        Self::new(vertices, indices)
+    }
+
+    /// A white UV sphere one unit wide, centered on the origin, with smooth
+    /// normals. `segments` splits it around the Y axis and `rings` from pole
+    /// to pole; the texture wraps around it once.
+    pub fn sphere(segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut vertices = Vec::with_capacity(((segments + 1) * (rings + 1)) as usize);
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let (sin_phi, cos_phi) = (v * std::f32::consts::PI).sin_cos();
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin_theta, cos_theta) = (u * std::f32::consts::TAU).sin_cos();
+                let normal = [sin_phi * cos_theta, cos_phi, sin_phi * sin_theta];
+                vertices.push(Vertex {
+                    position: normal.map(|n| n * 0.5),
+                    color: [1.0, 1.0, 1.0],
+                    normal,
+                    tex_coords: [u, v],
+                });
+            }
+        }
+
+        let row = segments + 1;
+        let indices = (0..rings)
+            .flat_map(|ring| (0..segments).map(move |segment| ring * row + segment))
+            .flat_map(|a| {
+                let (b, c, d) = (a + row, a + row + 1, a + 1);
+                [a, d, c, a, c, b]
+            })
+            .collect();
+
+        Self::new(vertices, indices)
+    }
+}
+
+/// Geometry uploaded to the GPU, drawn with `draw_indexed` once per submesh.
+pub struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    submeshes: Vec<Submesh>,
+    material: Option<MaterialId>,
+    bounds: Option<Aabb>,
+}
+
+impl Mesh {
+    pub fn new(device: &wgpu::Device, data: &MeshData) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Vertex Buffer"),
+            contents: bytemuck::cast_slice(&data.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Index Buffer"),
+            contents: bytemuck::cast_slice(&data.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            submeshes: data.submeshes.clone(),
+            material: None,
+            bounds: data.bounds(),
+        }
+    }
+
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    /// Local-space bounds of the uploaded vertices; `None` when there are none.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    /// The material the mesh is drawn with; `None` uses the renderer's default.
+    pub fn material(&self) -> Option<MaterialId> {
+        self.material
+    }
+
+    pub(crate) fn set_material(&mut self, material: Option<MaterialId>) {
+        self.material = material;
+    }
+
+    /// Binds the buffers and draws every submesh. The caller sets the pipeline
+    /// and bind groups.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for submesh in &self.submeshes {
+            render_pass.draw_indexed(submesh.indices.clone(), submesh.base_vertex, 0..1);
+        }
+    }
+}
+
+/// Handle to a mesh registered with [`Renderer::add_mesh`].
+/// Once the mesh is removed the handle never refers to anything again, even
+/// after a new mesh takes its place.
+///
+/// [`Renderer::add_mesh`]: crate::Renderer::add_mesh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshId(pub(crate) SlotKey);
@@ -0,0 +1,555 @@
+//! Wavefront OBJ and MTL loading.
+//!
+//! Supports `v`, `vt`, `vn`, `f` (with negative indices), `o`/`g`, `usemtl`
+//! and `mtllib`. Polygons are triangulated by ear clipping, and vertices
+//! without a normal get a smooth, area-weighted one. Each object/group and
+//! material combination becomes its own [`MeshData`].
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use crate::material::Material;
+use crate::mesh::MeshData;
+use crate::renderer::Renderer;
+use crate::scene::{NodeId, Transform};
+use crate::texture::{SamplerSettings, TextureData, TextureError};
+use crate::vertex::Vertex;
+
+/// Vertex color used when a mesh has no material.
+const DEFAULT_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
+/// Errors raised while loading an OBJ or MTL file.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file could not be read.
+    Io { path: PathBuf, source: std::io::Error },
+    /// A statement has missing or unparsable arguments.
+    Syntax { line: usize, message: String },
+    /// A face refers to a position, texture coordinate or normal that does not exist.
+    IndexOutOfBounds { line: usize, index: i64 },
+    /// A face has fewer than three vertices.
+    DegenerateFace { line: usize },
+    /// `usemtl` names a material that no loaded MTL file defines.
+    UnknownMaterial { line: usize, name: String },
+    /// An error inside an MTL file referenced with `mtllib`.
+    Mtl { path: PathBuf, error: Box<ObjError> },
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            ObjError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ObjError::IndexOutOfBounds { line, index } => {
+                write!(f, "line {}: index {} is out of bounds", line, index)
+            }
+            ObjError::DegenerateFace { line } => {
+                write!(f, "line {}: face has fewer than three vertices", line)
+            }
+            ObjError::UnknownMaterial { line, name } => {
+                write!(f, "line {}: unknown material '{}'", line, name)
+            }
+            ObjError::Mtl { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Mtl { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// A material from an MTL file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// `Ka`
+    pub ambient: [f32; 3],
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `Ks`
+    pub specular: [f32; 3],
+    /// `Ns`
+    pub shininess: f32,
+    /// `map_Kd`, resolved relative to the MTL file.
+    pub diffuse_texture: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            ambient: [0.0; 3],
+            diffuse: DEFAULT_COLOR,
+            specular: [0.0; 3],
+            shininess: 0.0,
+            diffuse_texture: None,
+        }
+    }
+
+    /// The engine material for meshes using this one. The diffuse color is
+    /// already baked into their vertex colors, so the base color is white.
+    pub fn to_material(&self) -> Material {
+        Material {
+            base_color: [1.0, 1.0, 1.0],
+            texture: None,
+            specular_strength: self.specular.into_iter().fold(0.0, f32::max),
+            shininess: self.shininess.max(1.0),
+            ..Material::default()
+        }
+    }
+}
+
+/// One object/group and material combination of an OBJ file.
+#[derive(Debug, Clone)]
+pub struct ObjMesh {
+    pub name: String,
+    /// Vertex colors are the material's diffuse color.
+    pub data: MeshData,
+    /// Index into [`ObjModel::materials`].
+    pub material: Option<usize>,
+}
+
+/// The contents of an OBJ file and the MTL files it references.
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    /// Uploads every mesh, material and diffuse texture and adds the model
+    /// under `parent`: one node placed by `transform`, with a child node per
+    /// mesh. Returns the model's node.
+    ///
+    /// Fails without touching the renderer if a texture cannot be loaded.
+    pub fn add_to_scene(
+        &self,
+        renderer: &mut Renderer,
+        parent: Option<NodeId>,
+        transform: Transform,
+    ) -> Result<NodeId, TextureError> {
+        let textures = self
+            .materials
+            .iter()
+            .map(|material| material.diffuse_texture.as_ref().map(TextureData::load).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        let material_ids: Vec<_> = self
+            .materials
+            .iter()
+            .zip(&textures)
+            .map(|(material, texture)| {
+                let texture = texture
+                    .as_ref()
+                    .map(|data| renderer.add_texture(data, SamplerSettings::default()));
+                renderer.add_material(Material {
+                    texture,
+                    ..material.to_material()
+                })
+            })
+            .collect();
+        let mesh_ids: Vec<_> = self
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let id = renderer.add_mesh(&mesh.data);
+                renderer.set_mesh_material(id, mesh.material.map(|m| material_ids[m]));
+                id
+            })
+            .collect();
+        let scene = renderer.scene_mut();
+        let root = scene.add_node(parent, transform, None);
+        for (mesh, id) in self.meshes.iter().zip(mesh_ids) {
+            let node = scene.add_node(Some(root), Transform::default(), Some(id));
+            scene.node_mut(node).unwrap().name = mesh.name.clone();
+        }
+        Ok(root)
+    }
+}
+
+/// Loads an OBJ file and the MTL files it references.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<ObjModel, ObjError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+    parse_obj(&source, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Parses OBJ source. `mtllib` paths are resolved relative to `base_dir`.
+pub fn parse_obj(source: &str, base_dir: &Path) -> Result<ObjModel, ObjError> {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+    let mut meshes = Vec::new();
+    let mut builder = MeshBuilder::new(String::new(), None, DEFAULT_COLOR);
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let content = raw_line.split('#').next().unwrap_or("");
+        let mut words = content.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        match keyword {
+            "v" => positions.push(Vector3::from(parse_floats::<3>(&args, line)?)),
+            "vn" => normals.push(Vector3::from(parse_floats::<3>(&args, line)?)),
+            "vt" => {
+                // `v` defaults to 0; OBJ puts it at the bottom of the image.
+                let [u] = parse_floats::<1>(&args, line)?;
+                let v = match args.get(1) {
+                    Some(_) => parse_floats::<2>(&args, line)?[1],
+                    None => 0.0,
+                };
+                tex_coords.push([u, 1.0 - v]);
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(ObjError::DegenerateFace { line });
+                }
+                let face = args
+                    .iter()
+                    .map(|arg| {
+                        parse_face_vertex(arg, line, positions.len(), tex_coords.len(), normals.len())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                builder.add_face(&face, &positions, &tex_coords, &normals);
+            }
+            "o" | "g" => {
+                let name = args.join(" ");
+                let next = MeshBuilder::new(name, builder.material, builder.color);
+                meshes.extend(std::mem::replace(&mut builder, next).finish());
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                let material = materials
+                    .iter()
+                    .position(|m| m.name == name)
+                    .ok_or(ObjError::UnknownMaterial { line, name })?;
+                if builder.material != Some(material) {
+                    let next = MeshBuilder::new(
+                        builder.name.clone(),
+                        Some(material),
+                        materials[material].diffuse,
+                    );
+                    meshes.extend(std::mem::replace(&mut builder, next).finish());
+                }
+            }
+            "mtllib" => {
+                for file in args {
+                    let path = base_dir.join(file);
+                    let source = read_file(&path)?;
+                    let dir = path.parent().unwrap_or(Path::new(""));
+                    let parsed = parse_mtl(&source, dir).map_err(|error| ObjError::Mtl {
+                        path: path.clone(),
+                        error: Box::new(error),
+                    })?;
+                    materials.extend(parsed);
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry are ignored.
+            _ => {}
+        }
+    }
+    meshes.extend(builder.finish());
+
+    Ok(ObjModel { meshes, materials })
+}
+
+/// Parses MTL source. Texture paths are resolved relative to `base_dir`.
+pub fn parse_mtl(source: &str, base_dir: &Path) -> Result<Vec<ObjMaterial>, ObjError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let content = raw_line.split('#').next().unwrap_or("");
+        let mut words = content.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(syntax(line, "expected a material name"));
+            }
+            materials.push(ObjMaterial::new(args.join(" ")));
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            return Err(syntax(line, &format!("'{}' before any 'newmtl'", keyword)));
+        };
+        match keyword {
+            "Ka" => material.ambient = parse_floats::<3>(&args, line)?,
+            "Kd" => material.diffuse = parse_floats::<3>(&args, line)?,
+            "Ks" => material.specular = parse_floats::<3>(&args, line)?,
+            "Ns" => material.shininess = parse_floats::<1>(&args, line)?[0],
+            // Options such as `-bm 1.0` precede the file name.
+            "map_Kd" => match args.last() {
+                Some(file) => material.diffuse_texture = Some(base_dir.join(file)),
+                None => return Err(syntax(line, "expected a texture path")),
+            },
+            // The engine draws opaque surfaces only, so `d` and `Tr` are ignored too.
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+fn read_file(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn syntax(line: usize, message: &str) -> ObjError {
+    ObjError::Syntax {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Parses the first `N` arguments as floats; extra arguments (such as the
+/// optional `w` of a position) are ignored.
+fn parse_floats<const N: usize>(args: &[&str], line: usize) -> Result<[f32; N], ObjError> {
+    if args.len() < N {
+        return Err(syntax(line, &format!("expected {} numbers, found {}", N, args.len())));
+    }
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg
+            .parse()
+            .map_err(|_| syntax(line, &format!("'{}' is not a number", arg)))?;
+    }
+    Ok(values)
+}
+
+/// Indices of one face corner, already resolved to zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    tex_coord: Option<usize>,
+    normal: Option<usize>,
+}
+
+fn parse_face_vertex(
+    arg: &str,
+    line: usize,
+    position_count: usize,
+    tex_coord_count: usize,
+    normal_count: usize,
+) -> Result<FaceVertex, ObjError> {
+    let mut parts = arg.split('/');
+    let resolve = |part: Option<&str>, count: usize| -> Result<Option<usize>, ObjError> {
+        let part = match part {
+            Some(part) if !part.is_empty() => part,
+            _ => return Ok(None),
+        };
+        let index: i64 = part
+            .parse()
+            .map_err(|_| syntax(line, &format!("'{}' is not a face index", arg)))?;
+        // OBJ indices are one-based; negative indices count back from the end.
+        let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(ObjError::IndexOutOfBounds { line, index });
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = resolve(parts.next(), position_count)?
+        .ok_or_else(|| syntax(line, &format!("'{}' has no position index", arg)))?;
+    let tex_coord = resolve(parts.next(), tex_coord_count)?;
+    let normal = resolve(parts.next(), normal_count)?;
+    if parts.next().is_some() {
+        return Err(syntax(line, &format!("'{}' has too many components", arg)));
+    }
+
+    Ok(FaceVertex {
+        position,
+        tex_coord,
+        normal,
+    })
+}
+
+/// Accumulates the faces of one object/group and material combination.
+struct MeshBuilder {
+    name: String,
+    material: Option<usize>,
+    color: [f32; 3],
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    lookup: HashMap<FaceVertex, u32>,
+    /// Summed face normals, per OBJ position, for vertices without a normal.
+    generated_normals: HashMap<usize, Vector3<f32>>,
+    /// Vertices that take their normal from `generated_normals`.
+    needs_normal: Vec<(u32, usize)>,
+}
+
+impl MeshBuilder {
+    fn new(name: String, material: Option<usize>, color: [f32; 3]) -> Self {
+        Self {
+            name,
+            material,
+            color,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            lookup: HashMap::new(),
+            generated_normals: HashMap::new(),
+            needs_normal: Vec::new(),
+        }
+    }
+
+    fn add_face(
+        &mut self,
+        face: &[FaceVertex],
+        positions: &[Vector3<f32>],
+        tex_coords: &[[f32; 2]],
+        normals: &[Vector3<f32>],
+    ) {
+        let points: Vec<_> = face.iter().map(|v| positions[v.position]).collect();
+        for [a, b, c] in triangulate(&points) {
+            // Unnormalized, so larger faces weigh more in smoothed normals.
+            let face_normal = (points[b] - points[a]).cross(points[c] - points[a]);
+            for corner in [a, b, c] {
+                let vertex = face[corner];
+                let index = self.vertex_index(vertex, positions, tex_coords, normals);
+                if vertex.normal.is_none() {
+                    *self.generated_normals.entry(vertex.position).or_insert_with(Vector3::zero) += face_normal;
+                }
+                self.indices.push(index);
+            }
+        }
+    }
+
+    fn vertex_index(
+        &mut self,
+        vertex: FaceVertex,
+        positions: &[Vector3<f32>],
+        tex_coords: &[[f32; 2]],
+        normals: &[Vector3<f32>],
+    ) -> u32 {
+        if let Some(&index) = self.lookup.get(&vertex) {
+            return index;
+        }
+        let index = self.vertices.len() as u32;
+        let normal = match vertex.normal {
+            Some(normal) => normals[normal].into(),
+            None => {
+                self.needs_normal.push((index, vertex.position));
+                [0.0; 3]
+            }
+        };
+        self.vertices.push(Vertex {
+            position: positions[vertex.position].into(),
+            color: self.color,
+            normal,
+            tex_coords: vertex.tex_coord.map_or([0.0; 2], |t| tex_coords[t]),
+        });
+        self.lookup.insert(vertex, index);
+        index
+    }
+
+    fn finish(mut self) -> Option<ObjMesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        for &(index, position) in &self.needs_normal {
+            let sum = self.generated_normals[&position];
+            let normal = if sum.magnitude2() > 0.0 { sum.normalize() } else { Vector3::unit_y() };
+            self.vertices[index as usize].normal = normal.into();
+        }
+        Some(ObjMesh {
+            name: self.name,
+            data: MeshData::new(self.vertices, self.indices),
+            material: self.material,
+        })
+    }
+}
+
+/// Splits a planar polygon into triangles by ear clipping, keeping the
+/// polygon's winding. Handles concave polygons; falls back to a fan when the
+/// polygon is degenerate.
+fn triangulate(points: &[Vector3<f32>]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method gives a robust normal even for concave polygons.
+    let mut normal = Vector3::<f32>::zero();
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    // Project onto the plane the polygon covers best.
+    let project = |p: Vector3<f32>| -> (f32, f32) {
+        let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+        if x >= y && x >= z {
+            (p.y, p.z)
+        } else if y >= z {
+            (p.z, p.x)
+        } else {
+            (p.x, p.y)
+        }
+    };
+    let flat: Vec<_> = points.iter().map(|&p| project(p)).collect();
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+
+    // Clip ears in counter-clockwise order, then restore the original winding.
+    let signed_area: f32 = (0..n).map(|i| cross((0.0, 0.0), flat[i], flat[(i + 1) % n])).sum();
+    let reversed = signed_area < 0.0;
+    let mut remaining: Vec<usize> = (0..n).collect();
+    if reversed {
+        remaining.reverse();
+    }
+    let emit = |a: usize, b: usize, c: usize| if reversed { [c, b, a] } else { [a, b, c] };
+
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|&i| {
+            let (prev, cur, next) = (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+            if cross(flat[prev], flat[cur], flat[next]) <= 0.0 {
+                return false;
+            }
+            remaining.iter().all(|&other| {
+                other == prev
+                    || other == cur
+                    || other == next
+                    || !point_in_triangle(flat[other], flat[prev], flat[cur], flat[next])
+            })
+        });
+        let Some(i) = ear else {
+            break;
+        };
+        let (prev, cur, next) = (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+        triangles.push(emit(prev, cur, next));
+        remaining.remove(i);
+    }
+    for i in 1..remaining.len() - 1 {
+        triangles.push(emit(remaining[0], remaining[i], remaining[i + 1]));
+    }
+    triangles
+}
+
+fn point_in_triangle(p: (f32, f32), a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> bool {
+    let sign = |p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)| {
+        (p1.0 - p3.0) * (p2.1 - p3.1) - (p2.0 - p3.0) * (p1.1 - p3.1)
+    };
+    let (d1, d2, d3) = (sign(p, a, b), sign(p, b, c), sign(p, c, a));
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_negative && has_positive)
+}
@@ -0,0 +1,116 @@
+//! Copying rendered textures back to the CPU.
+
+/// Errors raised while reading rendered pixels back to the CPU.
+#[derive(Debug)]
+pub enum ReadbackError {
+    /// The texture format has no RGBA8 conversion.
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// The staging buffer could not be mapped for reading.
+    Map(wgpu::BufferAsyncError),
+}
+
+impl std::fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadbackError::UnsupportedFormat(format) => {
+                write!(f, "cannot convert {:?} pixels to RGBA8", format)
+            }
+            ReadbackError::Map(e) => write!(f, "failed to map staging buffer: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+/// Reads back an 8-bit-per-channel color texture as RGBA8, swizzling BGRA
+/// surface formats. sRGB formats keep their encoded values, which is what
+/// image files expect.
+pub(crate) fn read_rgba8(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, ReadbackError> {
+    let swap_red_blue = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(ReadbackError::UnsupportedFormat(format)),
+    };
+
+    let mut pixels = read_texture(device, queue, texture)?;
+    if swap_red_blue {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
+
+/// Copies mip level 0 of `texture` into a staging buffer and returns its rows
+/// tightly packed, with the `COPY_BYTES_PER_ROW_ALIGNMENT` padding removed.
+///
+/// Blocks until the GPU has finished all submitted work.
+pub(crate) fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, ReadbackError> {
+    let width = texture.width();
+    let height = texture.height();
+    let bytes_per_pixel = texture
+        .format()
+        .block_size(None)
+        .expect("readback requires a single-aspect color format");
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let padded_bytes_per_row = unpadded_bytes_per_row
+        .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Staging Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &staging_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = staging_buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("map_async callback dropped without a result")
+        .map_err(ReadbackError::Map)?;
+
+    let padded = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    for row in padded.chunks(padded_bytes_per_row as usize) {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+    }
+    drop(padded);
+    staging_buffer.unmap();
+
+    Ok(pixels)
+}
@@ -1,49 +1,138 @@
 use wgpu::util::DeviceExt;
 use winit::window::Window;
 use winit::event::*;
-use cgmath::{Matrix4, Deg, SquareMatrix, Vector3};
 
-use crate::camera::{Camera, CameraController, CameraUniform};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use cgmath::Point3;
+
+use crate::camera::{Camera, CameraController, CameraMode};
+use crate::environment::{Environment, EnvironmentData, IblGenerator};
+use crate::hot_reload::{self, ShaderError, ShaderWatcher};
+use crate::input::{Action, ActionMap, Binding};
+use crate::light::{Light, LightBuffer, LightId};
+use crate::material::{self, GpuMaterial, Material, MaterialId};
+use crate::readback::{self, ReadbackError};
+use crate::screenshot::{self, CaptureError};
+use crate::mesh::{Aabb, Mesh, MeshData, MeshId};
+use crate::scene::{NodeId, Scene};
+use crate::slot::Slots;
+use crate::skybox::{Sky, Skybox};
+use crate::shadow::{ShadowMap, ShadowSettings};
+use crate::texture::{MipmapGenerator, SamplerSettings, Texture, TextureData, TextureId};
+use crate::tonemap::{TonemapSettings, Tonemapper, HDR_FORMAT};
+use crate::transform::TransformBuffer;
 use crate::vertex::Vertex;
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct TransformUniform {
-    model: [[f32; 4]; 4],
+crate::wgsl_struct! {
+    pub(crate) struct LightUniform {
+        position: [f32; 3],
+        color: [f32; 3],
+        ambient: f32,
+        diffuse: f32,
+        specular: f32,
+        light_space_matrix: [[f32; 4]; 4],
+        environment_intensity: f32,
+        max_reflection_lod: f32,
+        /// Non-zero when an environment replaces the flat ambient term.
+        has_environment: u32,
+    }
+}
+
+pub(crate) const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Features enabled when the adapter offers them.
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+/// Where a [`Renderer`] presents its frames.
+enum RenderTarget {
+    /// A window surface; each frame is presented to the screen.
+    Surface(wgpu::Surface),
+    /// An owned offscreen texture, drawn into by [`Renderer::render`].
+    Texture(wgpu::Texture),
+}
+
+/// Errors raised while creating a [`Renderer`] without a window.
+#[derive(Debug)]
+pub enum RendererError {
+    /// No adapter matched the requested options.
+    AdapterNotFound,
+    /// The adapter refused to create a device.
+    RequestDevice(wgpu::RequestDeviceError),
 }
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct LightUniform {
-    position: [f32; 3],
-    _padding1: u32,
-    color: [f32; 3],
-    _padding2: u32,
-    ambient: f32,
-    diffuse: f32,
-    specular: f32,
-    _padding3: u32,
-    light_space_matrix: [[f32; 4]; 4], 
+impl std::fmt::Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RendererError::AdapterNotFound => write!(f, "no suitable graphics adapter found"),
+            RendererError::RequestDevice(e) => write!(f, "failed to request device: {}", e),
+        }
+    }
 }
 
+impl std::error::Error for RendererError {}
+
 pub struct Renderer {
-    surface: wgpu::Surface,
+    target: RenderTarget,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
+    render_pipeline_layout: wgpu::PipelineLayout,
     render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
+    /// The main shader the pipeline was built from, kept to rebuild it when
+    /// the depth test changes.
+    shader: wgpu::ShaderModule,
+    /// Whether the pipeline tests depth for a reverse-Z projection.
+    reverse_z: bool,
+    /// Set in dev mode, when the main shader is loaded from disk.
+    shader_watcher: Option<ShaderWatcher>,
     camera: Camera,
     camera_controller: CameraController,
+    action_map: ActionMap,
+    /// Keys and buttons currently down, so the presses a held key repeats
+    /// do not perform its actions again.
+    held_bindings: HashSet<Binding>,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
-    rotation: f32,
-    transform_buffer: wgpu::Buffer,
-    transform_bind_group: wgpu::BindGroup,
+    transforms: TransformBuffer,
+    meshes: Slots<Mesh>,
+    material_bind_group_layout: wgpu::BindGroupLayout,
+    /// Used by meshes without a material, or whose material was removed.
+    default_material: GpuMaterial,
+    materials: Slots<GpuMaterial>,
+    mipmaps: MipmapGenerator,
+    /// Bound by materials without a texture, or whose texture was removed.
+    default_texture: Texture,
+    textures: Slots<Texture>,
+    scene: Scene,
+    /// Mesh and transform slot of every node drawn this frame.
+    draw_list: Vec<(MeshId, usize)>,
+    light_uniform: LightUniform,
     light_buffer: wgpu::Buffer,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     light_bind_group: wgpu::BindGroup,
+    lights: LightBuffer,
+    shadow_map: ShadowMap,
+    /// `None` when the device cannot run compute shaders.
+    ibl: Option<IblGenerator>,
+    environment: Option<Environment>,
+    /// Bound in place of the environment's maps while none is set.
+    placeholder_environment: Environment,
+    environment_sampler: wgpu::Sampler,
+    /// Draws the background once the scene is drawn.
+    skybox: Skybox,
+    /// The HDR target the scene is drawn into, and the pass mapping it to the frame.
+    tonemapper: Tonemapper,
     depth_texture: wgpu::Texture,
     depth_view: wgpu::TextureView,
+    /// MSAA samples per pixel of the color and depth targets.
+    sample_count: u32,
+    /// Sample counts both the HDR format and the depth format support.
+    supported_sample_counts: Vec<u32>,
+    /// The multisampled color target, resolved into the HDR target. `None`
+    /// without MSAA.
+    msaa_view: Option<wgpu::TextureView>,
     pub size: winit::dpi::PhysicalSize<u32>,
 }
 
@@ -69,7 +158,7 @@ impl Renderer {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    features: wgpu::Features::empty(),
+                    features: adapter.features() & OPTIONAL_FEATURES,
                     limits: wgpu::Limits::default(),
                 },
                 None,
@@ -97,8 +186,72 @@ impl Renderer {
 
         surface.configure(&device, &config);
 
+        Self::from_device(&adapter, device, queue, RenderTarget::Surface(surface), config)
+    }
+
+    /// Creates a renderer without a window that draws into an owned RGBA8
+    /// texture. Use [`Renderer::read_pixels`] to fetch the rendered frame.
+    ///
+    /// Set `force_fallback_adapter` to run on the software adapter, e.g. on CI
+    /// machines without a GPU or display.
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, RendererError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or(RendererError::AdapterNotFound)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: adapter.features() & OPTIONAL_FEATURES,
+                    limits: wgpu::Limits::default(),
+                },
+                None,
+            )
+            .await
+            .map_err(RendererError::RequestDevice)?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        let texture = create_target_texture(&device, &config);
+
+        Ok(Self::from_device(&adapter, device, queue, RenderTarget::Texture(texture), config))
+    }
+
+    /// Builds the pipeline, bind groups and scene shared by the windowed and
+    /// headless constructors.
+    fn from_device(
+        adapter: &wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: RenderTarget,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
         let camera = Camera::new(config.width, config.height);
-        let camera_controller = CameraController::new(0.2, 0.4);
+        let camera_controller = CameraController::new(5.0, 0.4);
         let camera_uniform = camera.build_view_projection_matrix();
 
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -130,53 +283,23 @@ impl Renderer {
             }],
         });
 
-        let transform_uniform = TransformUniform {
-            model: Matrix4::identity().into(),
-        };
-
-        let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Transform Buffer"),
-            contents: bytemuck::cast_slice(&[transform_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let transform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Transform Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
-
-        let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Transform Bind Group"),
-            layout: &transform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: transform_buffer.as_entire_binding(),
-            }],
-        });
+        let transforms = TransformBuffer::new(&device, 2);
+        let shadow_settings = ShadowSettings::default();
 
         // Create light uniform and buffer
         // In the Renderer::new method, modify the light_uniform:
-        let light_uniform = LightUniform {
-            position: [5.0, 5.0, 5.0],  // Move light further out
-            _padding1: 0,
-            color: [1.0, 1.0, 1.0],     // Full white light
-            _padding2: 0,
-            ambient: 0.3,               // Increased ambient
-            diffuse: 1.2,               // Increased diffuse
-            specular: 0.8,              // Increased specular
-            _padding3: 0,
-            light_space_matrix: Matrix4::identity().into(), // Identity matrix for now
-        };
+        let mut light_uniform = LightUniform::new(
+            [5.0, 5.0, 5.0],  // Move light further out
+            [1.0, 1.0, 1.0],  // Full white light
+            0.3,              // Increased ambient
+            1.2,              // Increased diffuse
+            0.8,              // Increased specular
+            [[0.0; 4]; 4],
+            1.0,
+            0.0,
+            0,
+        );
+        light_uniform.light_space_matrix = shadow_settings.light_space_matrix(light_uniform.position).into();
 
         let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Light Buffer"),
@@ -186,26 +309,91 @@ impl Renderer {
 
         let light_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Light Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Irradiance and prefiltered specular cubemaps, then the BRDF lookup table
+                environment_texture_entry(4, wgpu::TextureViewDimension::Cube),
+                environment_texture_entry(5, wgpu::TextureViewDimension::Cube),
+                environment_texture_entry(6, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
         });
 
-        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Light Bind Group"),
-            layout: &light_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: light_buffer.as_entire_binding(),
-            }],
+        let shadow_map = ShadowMap::new(&device, shadow_settings, &light_buffer, transforms.layout());
+        let lights = LightBuffer::new(&device, 4);
+
+        let compute = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        let ibl = compute.then(|| IblGenerator::new(&device, &queue));
+        let placeholder_environment = Environment::placeholder(&device);
+        let environment_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
         });
+        let light_bind_group = create_light_bind_group(
+            &device,
+            &light_bind_group_layout,
+            &light_buffer,
+            &shadow_map,
+            &lights,
+            &placeholder_environment,
+            &environment_sampler,
+        );
+
+        let mipmaps = MipmapGenerator::new(&device);
+        let default_texture = Texture::white(&device, &queue, &mipmaps);
+        let material_bind_group_layout = material::create_bind_group_layout(&device);
+        let default_material = GpuMaterial::new(
+            &device,
+            &material_bind_group_layout,
+            Material::default(),
+            &default_texture,
+        );
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -216,299 +404,77 @@ impl Renderer {
             label: Some("Render Pipeline Layout"),
             bind_group_layouts: &[
                 &camera_bind_group_layout,
-                &transform_bind_group_layout,
+                transforms.layout(),
                 &light_bind_group_layout,
+                &material_bind_group_layout,
             ],
             push_constant_ranges: &[],
         });
 
+        let supported_sample_counts = supported_sample_counts(adapter, &device, HDR_FORMAT);
+        let sample_count = 1;
+        let (depth_texture, depth_view) = create_depth_texture(&device, &config, sample_count);
 
-let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-    label: Some("Depth Texture"),
-    size: wgpu::Extent3d {
-        width: config.width,
-        height: config.height,
-        depth_or_array_layers: 1,
-    },
-    mip_level_count: 1,
-    sample_count: 1,
-    dimension: wgpu::TextureDimension::D2,
-    format: wgpu::TextureFormat::Depth32Float,
-    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-    view_formats: &[],
-});
-let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-    label: Some("Render Pipeline"),
-    layout: Some(&render_pipeline_layout),
-    vertex: wgpu::VertexState {
-        module: &shader,
-        entry_point: "vs_main",
-        buffers: &[Vertex::desc()], 
-    },
-    fragment: Some(wgpu::FragmentState {
-        module: &shader,
-        entry_point: "fs_main",
-        targets: &[Some(wgpu::ColorTargetState {
-            format: config.format,
-            blend: Some(wgpu::BlendState::REPLACE),
-            write_mask: wgpu::ColorWrites::ALL,
-        })],
-    }),
-    primitive: wgpu::PrimitiveState {
-        topology: wgpu::PrimitiveTopology::TriangleList,
-        strip_index_format: None,
-        front_face: wgpu::FrontFace::Ccw,
-        cull_mode: None,
-        unclipped_depth: false,
-        polygon_mode: wgpu::PolygonMode::Fill,
-        conservative: false,
-    },
-    depth_stencil: Some(wgpu::DepthStencilState {
-        format: wgpu::TextureFormat::Depth32Float,
-        depth_write_enabled: true,
-        depth_compare: wgpu::CompareFunction::Less,
-        stencil: wgpu::StencilState::default(),
-        bias: wgpu::DepthBiasState::default(),
-    }),
-    multisample: wgpu::MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-    },
-    multiview: None,
-});
-
-
-
-        // Define all vertices for the scene
-        let vertices = [
-            // Front face of pyramid
-            Vertex {
-                position: [0.0, 1.0, 0.0],      // Top vertex
-                color: [1.0, 0.0, 0.0],         // Red
-                normal: [0.0, 0.5, 1.0],
-            },
-            Vertex {
-                position: [-0.5, -0.5, 0.5],    // Bottom left
-                color: [0.0, 1.0, 0.0],         // Green
-                normal: [0.0, 0.5, 1.0],
-            },
-            Vertex {
-                position: [0.5, -0.5, 0.5],     // Bottom right
-                color: [0.0, 0.0, 1.0],         // Blue
-                normal: [0.0, 0.5, 1.0],
-            },
-
-            // Right face of pyramid
-            Vertex {
-                position: [0.0, 1.0, 0.0],      // Top vertex
-                color: [1.0, 1.0, 0.0],         // Yellow
-                normal: [1.0, 0.5, 0.0],
-            },
-            Vertex {
-                position: [0.5, -0.5, 0.5],     // Bottom front
-                color: [1.0, 0.0, 1.0],         // Magenta
-                normal: [1.0, 0.5, 0.0],
-            },
-            Vertex {
-                position: [0.5, -0.5, -0.5],    // Bottom back
-                color: [0.0, 1.0, 1.0],         // Cyan
-                normal: [1.0, 0.5, 0.0],
-            },
-
-            // Back face of pyramid
-            Vertex {
-                position: [0.0, 1.0, 0.0],      // Top vertex
-                color: [0.5, 0.5, 0.5],         // Gray
-                normal: [0.0, 0.5, -1.0],
-            },
-            Vertex {
-                position: [0.5, -0.5, -0.5],    // Bottom right
-                color: [0.7, 0.2, 0.3],         // Dark Pink
-                normal: [0.0, 0.5, -1.0],
-            },
-            Vertex {
-                position: [-0.5, -0.5, -0.5],   // Bottom left
-                color: [0.2, 0.7, 0.3],         // Dark Green
-                normal: [0.0, 0.5, -1.0],
-            },
-
-            // Left face of pyramid
-            Vertex {
-                position: [0.0, 1.0, 0.0],      // Top vertex
-                color: [0.3, 0.7, 0.5],         // Teal
-                normal: [-1.0, 0.5, 0.0],
-            },
-            Vertex {
-                position: [-0.5, -0.5, -0.5],   // Bottom back
-                color: [0.8, 0.6, 0.2],         // Brown
-                normal: [-1.0, 0.5, 0.0],
-            },
-            Vertex {
-                position: [-0.5, -0.5, 0.5],    // Bottom front
-                color: [0.4, 0.4, 0.8],         // Indigo
-                normal: [-1.0, 0.5, 0.0],
-            },
-
-            // Bottom face of pyramid - Triangle 1
-            Vertex {
-                position: [-0.5, -0.5, 0.5],    // Front left
-                color: [0.5, 0.2, 0.7],         // Purple
-                normal: [0.0, -1.0, 0.0],
-            },
-            Vertex {
-                position: [0.5, -0.5, 0.5],     // Front right
-                color: [0.2, 0.5, 0.7],         // Blue-Green
-                normal: [0.0, -1.0, 0.0],
-            },
-            Vertex {
-                position: [0.5, -0.5, -0.5],    // Back right
-                color: [0.7, 0.5, 0.2],         // Orange
-                normal: [0.0, -1.0, 0.0],
-            },
-
-            // Bottom face of pyramid - Triangle 2
-            Vertex {
-                position: [0.5, -0.5, -0.5],    // Back right
-                color: [0.7, 0.5, 0.2],         // Orange
-                normal: [0.0, -1.0, 0.0],
-            },
-            Vertex {
-                position: [-0.5, -0.5, -0.5],   // Back left
-                color: [0.3, 0.6, 0.1],         // Lime Green
-                normal: [0.0, -1.0, 0.0],
-            },
-            Vertex {
-                position: [-0.5, -0.5, 0.5],    // Front left
-                color: [0.5, 0.2, 0.7],         // Purple
-                normal: [0.0, -1.0, 0.0],
-            },
-
-            // Ground plane - Front section
-            Vertex {
-                position: [-20.0, -1.5, -20.0],
-                color: [0.2, 0.5, 0.2],         // Base green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, -20.0],
-                color: [0.22, 0.55, 0.22],      // Slightly lighter green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, -10.0],
-                color: [0.25, 0.6, 0.25],       // Varied green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [-20.0, -1.5, -20.0],
-                color: [0.2, 0.5, 0.2],         // Base green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, -10.0],
-                color: [0.25, 0.6, 0.25],       // Varied green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [-20.0, -1.5, -10.0],
-                color: [0.27, 0.65, 0.27],      // Another green variation
-                normal: [0.0, 1.0, 0.0],
-            },
-
-            // Ground plane - Middle section
-            Vertex {
-                position: [-20.0, -1.5, -10.0],
-                color: [0.25, 0.6, 0.25],       // Varied green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, -10.0],
-                color: [0.25, 0.6, 0.25],       // Varied green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, 10.0],
-                color: [0.3, 0.65, 0.3],        // Slightly brighter green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [-20.0, -1.5, -10.0],
-                color: [0.25, 0.6, 0.25],       // Varied green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, 10.0],
-                color: [0.3, 0.65, 0.3],        // Slightly brighter green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [-20.0, -1.5, 10.0],
-                color: [0.32, 0.7, 0.32],       // Brighter green variation
-                normal: [0.0, 1.0, 0.0],
-            },
-
-            // Ground plane - Back section
-            Vertex {
-                position: [-20.0, -1.5, 10.0],
-                color: [0.3, 0.65, 0.3],        // Slightly brighter green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, 10.0],
-                color: [0.3, 0.65, 0.3],        // Slightly brighter green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, 20.0],
-                color: [0.2, 0.55, 0.2],        // Base green variation
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [-20.0, -1.5, 10.0],
-                color: [0.3, 0.65, 0.3],        // Slightly brighter green
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [20.0, -1.5, 20.0],
-                color: [0.2, 0.55, 0.2],        // Base green variation
-                normal: [0.0, 1.0, 0.0],
-            },
-            Vertex {
-                position: [-20.0, -1.5, 20.0],
-                color: [0.2, 0.5, 0.2],         // Base green
-                normal: [0.0, 1.0, 0.0],
-            },
-            ];
+        let reverse_z = camera.projection().reverse_z();
+        let render_pipeline = create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            reverse_z,
+            sample_count,
+        );
+        let skybox = Skybox::new(&device, reverse_z, sample_count);
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let tonemapper = Tonemapper::new(&device, config.width, config.height, config.format, compute);
 
-        Self {
-            surface,
+        let mut renderer = Self {
+            target,
             device,
             queue,
             config,
             size,
+            render_pipeline_layout,
             render_pipeline,
-            vertex_buffer,
+            shader,
+            reverse_z,
+            shader_watcher: None,
             camera,
             camera_controller,
+            action_map: ActionMap::default(),
+            held_bindings: HashSet::new(),
             camera_buffer,
             camera_bind_group,
-            rotation: 0.0,
-            transform_buffer,
-            transform_bind_group,
+            transforms,
+            meshes: Slots::new(),
+            material_bind_group_layout,
+            default_material,
+            materials: Slots::new(),
+            mipmaps,
+            default_texture,
+            textures: Slots::new(),
+            scene: Scene::new(),
+            draw_list: Vec::new(),
+            light_uniform,
             light_buffer,
+            light_bind_group_layout,
             light_bind_group,
+            lights,
+            shadow_map,
+            ibl,
+            environment: None,
+            placeholder_environment,
+            environment_sampler,
+            skybox,
+            tonemapper,
             depth_texture,
             depth_view,
-        }
+            sample_count,
+            supported_sample_counts,
+            msaa_view: None,
+        };
+        renderer.update_transforms();
+        renderer.upload_lights();
+        renderer
     }
 
 pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -516,31 +482,23 @@ pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.size = new_size;
         self.config.width = new_size.width;
         self.config.height = new_size.height;
-        self.surface.configure(&self.device, &self.config);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            RenderTarget::Texture(texture) => {
+                *texture = create_target_texture(&self.device, &self.config);
+            }
+        }
         
-        // Recreate depth texture with new size
-        self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
-            size: wgpu::Extent3d {
-                width: new_size.width,
-                height: new_size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-        self.depth_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Recreate the HDR, depth and MSAA targets with the new size
+        self.tonemapper.resize(&self.device, new_size.width, new_size.height);
+        self.recreate_frame_targets();
         
         self.camera.resize(new_size.width, new_size.height);
     }
 }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
+        let (binding, state) = match event {
             WindowEvent::KeyboardInput {
                 input: KeyboardInput {
                     state,
@@ -548,14 +506,92 @@ pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
                     ..
                 },
                 ..
-            } => self.camera_controller.process_keyboard(*key, *state),
-            _ => false
+            } => (Binding::from(*key), *state),
+            WindowEvent::MouseInput { button, state, .. } => (Binding::from(*button), *state),
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.camera_controller.process_scroll(delta);
+                return true;
+            }
+            _ => return false,
+        };
+        self.process_binding(binding, state)
+    }
+
+    /// Performs the actions bound to `binding` as it is pressed or released.
+    ///
+    /// An action counts as held while any of its bindings is down: it starts
+    /// on the first of them to be pressed and stops once the last is
+    /// released. Repeated presses of a held key do nothing.
+    pub fn process_binding(&mut self, binding: Binding, state: ElementState) -> bool {
+        let changed = match state {
+            ElementState::Pressed => self.held_bindings.insert(binding),
+            ElementState::Released => self.held_bindings.remove(&binding),
+        };
+        let actions: Vec<_> = self.action_map.actions(binding).collect();
+        let mut handled = false;
+        for action in actions {
+            let held_elsewhere = self
+                .action_map
+                .bindings(action)
+                .iter()
+                .any(|other| *other != binding && self.held_bindings.contains(other));
+            handled |= if changed && !held_elsewhere {
+                self.process_action(action, state)
+            } else {
+                true
+            };
+        }
+        handled
+    }
+
+    /// Performs `action` as if it was pressed or released. Unlike
+    /// [`Renderer::process_binding`], every press counts. Returns `false` if
+    /// the action had no effect.
+    pub fn process_action(&mut self, action: Action, state: ElementState) -> bool {
+        let pressed = state == ElementState::Pressed;
+        match action {
+            Action::Screenshot => {
+                if pressed {
+                    let path = screenshot::timestamped_path();
+                    match self.capture_frame(&path) {
+                        Ok(()) => eprintln!("Saved screenshot to {}", path.display()),
+                        Err(e) => eprintln!("Failed to save screenshot: {}", e),
+                    }
+                }
+                true
+            }
+            Action::ToggleCameraMode => {
+                if pressed {
+                    let mode = match self.camera.mode() {
+                        CameraMode::Fly => CameraMode::Orbit,
+                        CameraMode::Orbit => CameraMode::Fly,
+                    };
+                    self.camera.set_mode(mode);
+                }
+                true
+            }
+            Action::FrameScene => {
+                if pressed {
+                    self.frame_scene();
+                }
+                true
+            }
+            _ => self.camera_controller.process_action(action, state),
         }
     }
 
-    pub fn update(&mut self) {
+    /// Releases every held key and button, e.g. when the window loses focus
+    /// and would miss the release events.
+    pub fn release_input(&mut self) {
+        self.held_bindings.clear();
+        self.camera_controller.release();
+    }
+
+    /// Advances the camera by `dt`, the time since the previous frame, and
+    /// uploads the frame's camera, transforms and lights.
+    pub fn update(&mut self, dt: Duration) {
     // Update camera
-    self.camera.update(&self.camera_controller);
+    self.camera.update(&self.camera_controller, dt);
     let camera_uniform = self.camera.build_view_projection_matrix();
     self.queue.write_buffer(
         &self.camera_buffer,
@@ -566,46 +602,545 @@ pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
     // Reset mouse movement
     self.camera_controller.reset_mouse_movement();
 
-    // Increase rotation speed and add more dynamic rotation
-    self.rotation += 0.0; // Increase rotation speed
-    
-    // Create a more complex rotation matrix
-    let model = Matrix4::from_angle_x(Deg(self.rotation * 0.7)) * 
-                Matrix4::from_angle_y(Deg(self.rotation)) *
-                Matrix4::from_angle_z(Deg(self.rotation * 0.3));
-    
-    let transform_uniform = TransformUniform {
-        model: model.into(),
-    };
-    self.queue.write_buffer(
-        &self.transform_buffer,
-        0,
-        bytemuck::cast_slice(&[transform_uniform]),
-    );
+    let reverse_z = self.camera.projection().reverse_z();
+    if reverse_z != self.reverse_z {
+        self.reverse_z = reverse_z;
+        self.rebuild_pipeline();
+    }
+
+    self.update_transforms();
+    self.upload_lights();
+    self.skybox.update(&self.queue, &self.camera, self.light_uniform.environment_intensity);
+    self.tonemapper.update(&self.queue, dt);
+
+    if let Err(e) = self.reload_shader() {
+        eprintln!("{}", e);
+    }
 }
 
+    /// Dev mode: loads the main shader from `path` instead of the copy built
+    /// into the engine, and rebuilds the pipeline in place whenever the file
+    /// changes. Changes are picked up by [`Renderer::update`].
+    ///
+    /// Returns the result of the first load. The file stays watched either
+    /// way, so fixing a broken shader on disk takes effect on the next update.
+    pub fn watch_shader(&mut self, path: impl Into<std::path::PathBuf>) -> Result<(), ShaderError> {
+        self.shader_watcher = Some(ShaderWatcher::new(path));
+        self.reload_shader().map(|_| ())
+    }
+
+    /// Rebuilds the pipeline if the watched shader changed on disk. Returns
+    /// `Ok(false)` when nothing changed or no shader is watched.
+    ///
+    /// On error the previous pipeline stays active.
+    pub fn reload_shader(&mut self) -> Result<bool, ShaderError> {
+        let Some(watcher) = &mut self.shader_watcher else {
+            return Ok(false);
+        };
+        let Some(source) = watcher.poll()? else {
+            return Ok(false);
+        };
+        hot_reload::validate(&source, watcher.path())?;
+
+        // naga accepted the shader, but wgpu still checks it against the
+        // pipeline layout and vertex format.
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            &shader,
+            self.reverse_z,
+            self.sample_count,
+        );
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(ShaderError::Pipeline(error.to_string()));
+        }
+        self.render_pipeline = pipeline;
+        self.shader = shader;
+        Ok(true)
+    }
+
+    /// Rebuilds the pipeline from the current shader after the depth test or
+    /// sample count changed.
+    fn rebuild_pipeline(&mut self) {
+        self.render_pipeline = create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            &self.shader,
+            self.reverse_z,
+            self.sample_count,
+        );
+        self.skybox.rebuild_pipeline(&self.device, self.reverse_z, self.sample_count);
+    }
+
+    /// Recreates the depth target and, with MSAA, the multisampled color
+    /// target at the current size and sample count.
+    fn recreate_frame_targets(&mut self) {
+        (self.depth_texture, self.depth_view) = create_depth_texture(&self.device, &self.config, self.sample_count);
+        self.msaa_view = (self.sample_count > 1).then(|| create_msaa_view(&self.device, &self.config, self.sample_count));
+    }
+
+    /// MSAA samples per pixel; 1 when multisampling is off.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// The sample counts [`Renderer::set_sample_count`] accepts on this adapter,
+    /// in ascending order. Always contains 1.
+    pub fn supported_sample_counts(&self) -> &[u32] {
+        &self.supported_sample_counts
+    }
+
+    /// Switches multisample anti-aliasing to `count` samples per pixel, or off
+    /// with 1. Returns `false`, changing nothing, if the adapter does not support
+    /// `count` for the color and depth formats.
+    pub fn set_sample_count(&mut self, count: u32) -> bool {
+        if !self.supported_sample_counts.contains(&count) {
+            return false;
+        }
+        if count != self.sample_count {
+            self.sample_count = count;
+            self.recreate_frame_targets();
+            self.rebuild_pipeline();
+        }
+        true
+    }
+
+    pub fn tonemap_settings(&self) -> &TonemapSettings {
+        self.tonemapper.settings()
+    }
+
+    /// Whether [`TonemapSettings::auto_exposure`] is available; it needs compute
+    /// shaders.
+    pub fn supports_auto_exposure(&self) -> bool {
+        self.tonemapper.supports_auto_exposure()
+    }
+
+    /// Changes the tonemapping operator, exposure or gamma. Returns `false`,
+    /// changing nothing, if the settings enable auto-exposure but the adapter
+    /// does not support it.
+    pub fn set_tonemap_settings(&mut self, settings: TonemapSettings) -> bool {
+        self.tonemapper.set_settings(&self.queue, settings)
+    }
+
+    /// Walks the scene graph and uploads the world matrix of every node with a mesh.
+    fn update_transforms(&mut self) {
+        self.scene.update_world_matrices();
+        self.transforms.clear();
+        self.draw_list.clear();
+        for (mesh, world) in self.scene.drawables() {
+            if self.meshes.contains(mesh.0) {
+                let slot = self.transforms.push(world);
+                self.draw_list.push((mesh, slot));
+            }
+        }
+        self.transforms.upload(&self.device, &self.queue);
+    }
+
+    /// Uploads `data` to the GPU and returns a handle for attaching it to
+    /// scene nodes.
+    pub fn add_mesh(&mut self, data: &MeshData) -> MeshId {
+        let mesh = Mesh::new(&self.device, data);
+        MeshId(self.meshes.insert(mesh))
+    }
+
+    /// Frees the mesh's buffers. Nodes still referring to it are no longer drawn.
+    pub fn remove_mesh(&mut self, id: MeshId) -> Option<Mesh> {
+        self.meshes.remove(id.0)
+    }
+
+    pub fn mesh(&self, id: MeshId) -> Option<&Mesh> {
+        self.meshes.get(id.0)
+    }
+
+    /// Draws `mesh` with `material`, or with the default material when `None`.
+    /// Returns `false` if the mesh was removed.
+    pub fn set_mesh_material(&mut self, mesh: MeshId, material: Option<MaterialId>) -> bool {
+        match self.meshes.get_mut(mesh.0) {
+            Some(mesh) => {
+                mesh.set_material(material);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Uploads `material` and returns a handle for assigning it to meshes.
+    pub fn add_material(&mut self, material: Material) -> MaterialId {
+        let texture = self.material_texture(&material);
+        let material = GpuMaterial::new(&self.device, &self.material_bind_group_layout, material, texture);
+        MaterialId(self.materials.insert(material))
+    }
+
+    /// Frees the material. Meshes still using it fall back to the default material.
+    pub fn remove_material(&mut self, id: MaterialId) -> Option<Material> {
+        let material = self.materials.remove(id.0)?;
+        Some(*material.material())
+    }
+
+    pub fn material(&self, id: MaterialId) -> Option<&Material> {
+        Some(self.materials.get(id.0)?.material())
+    }
+
+    /// Replaces a material's properties. Returns `false` if it was removed.
+    pub fn set_material(&mut self, id: MaterialId, material: Material) -> bool {
+        let Some(old) = self.materials.get(id.0) else {
+            return false;
+        };
+        if old.material().texture == material.texture {
+            self.materials.get_mut(id.0).unwrap().set(&self.queue, material);
+        } else {
+            let texture = self.material_texture(&material);
+            let rebound = GpuMaterial::new(&self.device, &self.material_bind_group_layout, material, texture);
+            *self.materials.get_mut(id.0).unwrap() = rebound;
+        }
+        true
+    }
+
+    /// The texture `material` samples, or the white default texture.
+    fn material_texture(&self, material: &Material) -> &Texture {
+        material
+            .texture
+            .and_then(|id| self.textures.get(id.0))
+            .unwrap_or(&self.default_texture)
+    }
+
+    /// Uploads `data`, generates its mipmaps and returns a handle for use in
+    /// [`Material::texture`].
+    pub fn add_texture(&mut self, data: &TextureData, sampler: SamplerSettings) -> TextureId {
+        let texture = Texture::new(&self.device, &self.queue, &self.mipmaps, data, sampler);
+        TextureId(self.textures.insert(texture))
+    }
+
+    /// Frees the texture. Materials already using it keep drawing with it
+    /// until [`Renderer::set_material`] gives them a different texture.
+    pub fn remove_texture(&mut self, id: TextureId) -> Option<Texture> {
+        self.textures.remove(id.0)
+    }
+
+    pub fn texture(&self, id: TextureId) -> Option<&Texture> {
+        self.textures.get(id.0)
+    }
+
+    /// The bind group of `mesh`'s material, or of the default material.
+    fn material_bind_group(&self, mesh: &Mesh) -> &wgpu::BindGroup {
+        mesh.material()
+            .and_then(|id| self.materials.get(id.0))
+            .unwrap_or(&self.default_material)
+            .bind_group()
+    }
+
+    /// Moves the scene light to `position` and changes its color.
+    pub fn set_light(&mut self, position: [f32; 3], color: [f32; 3]) {
+        self.light_uniform.position = position;
+        self.light_uniform.color = color;
+        self.write_light();
+    }
+
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        self.shadow_map.settings()
+    }
+
+    /// Changes the shadow map size, depth bias or covered area.
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        if self.shadow_map.set_settings(&self.device, settings) {
+            self.rebuild_light_bind_group();
+        }
+        self.write_light();
+    }
+
+    /// Adds a dynamic light, shaded from the next [`Renderer::update`].
+    pub fn add_light(&mut self, light: Light) -> LightId {
+        self.lights.add(light)
+    }
+
+    pub fn remove_light(&mut self, id: LightId) -> Option<Light> {
+        self.lights.remove(id)
+    }
+
+    pub fn light(&self, id: LightId) -> Option<&Light> {
+        self.lights.get(id)
+    }
+
+    /// Mutable access to a dynamic light. Changes reach the GPU on the next [`Renderer::update`].
+    pub fn light_mut(&mut self, id: LightId) -> Option<&mut Light> {
+        self.lights.get_mut(id)
+    }
+
+    /// Moves a dynamic light. Returns `false` if it was removed.
+    pub fn move_light(&mut self, id: LightId, position: Point3<f32>) -> bool {
+        match self.lights.get_mut(id) {
+            Some(light) => {
+                light.position = position;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over every dynamic light.
+    pub fn lights(&self) -> impl Iterator<Item = (LightId, &Light)> {
+        self.lights.iter()
+    }
+
+    fn upload_lights(&mut self) {
+        if self.lights.upload(&self.device, &self.queue) {
+            self.rebuild_light_bind_group();
+        }
+    }
+
+    fn rebuild_light_bind_group(&mut self) {
+        self.light_bind_group = create_light_bind_group(
+            &self.device,
+            &self.light_bind_group_layout,
+            &self.light_buffer,
+            &self.shadow_map,
+            &self.lights,
+            self.environment.as_ref().unwrap_or(&self.placeholder_environment),
+            &self.environment_sampler,
+        );
+    }
+
+    /// Lights the scene with `data` instead of the flat ambient term: diffuse
+    /// light from its irradiance, and reflections on metallic-roughness
+    /// materials. Replaces any previous environment. Images larger than the
+    /// device's texture size limit are downsampled first.
+    ///
+    /// Returns `false`, changing nothing, if the adapter cannot run the
+    /// compute shaders that precompute the lighting.
+    pub fn set_environment(&mut self, data: &EnvironmentData) -> bool {
+        let Some(ibl) = &self.ibl else {
+            return false;
+        };
+        let environment = ibl.generate(&self.device, &self.queue, data);
+        self.light_uniform.max_reflection_lod = environment.max_reflection_lod();
+        self.light_uniform.has_environment = 1;
+        self.environment = Some(environment);
+        self.skybox.set_environment(&self.device, self.environment.as_ref());
+        self.rebuild_light_bind_group();
+        self.write_light();
+        true
+    }
+
+    /// Returns to the flat ambient term.
+    pub fn remove_environment(&mut self) -> Option<Environment> {
+        let environment = self.environment.take()?;
+        self.skybox.set_environment(&self.device, None);
+        self.light_uniform.has_environment = 0;
+        self.rebuild_light_bind_group();
+        self.write_light();
+        Some(environment)
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    pub fn sky(&self) -> &Sky {
+        self.skybox.sky()
+    }
+
+    /// Changes what is drawn behind the scene.
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.skybox.set_sky(sky);
+    }
+
+    /// Uploads an equirectangular image, such as a loaded `.hdr` file, and
+    /// draws it behind the scene as [`Sky::Image`]. Images larger than the
+    /// device's texture size limit are downsampled first.
+    pub fn set_sky_image(&mut self, data: &EnvironmentData) {
+        self.skybox.set_image(&self.device, &self.queue, data);
+        self.skybox.set_sky(Sky::Image);
+    }
+
+    /// Whether [`Renderer::set_environment`] is available; it needs compute shaders.
+    pub fn supports_environment(&self) -> bool {
+        self.ibl.is_some()
+    }
+
+    pub fn environment_intensity(&self) -> f32 {
+        self.light_uniform.environment_intensity
+    }
+
+    /// Scales the light received from the environment.
+    pub fn set_environment_intensity(&mut self, intensity: f32) {
+        self.light_uniform.environment_intensity = intensity;
+        self.write_light();
+    }
+
+    /// Recomputes the light's shadow projection and uploads the light uniform.
+    fn write_light(&mut self) {
+        let settings = self.shadow_map.settings();
+        self.light_uniform.light_space_matrix = settings.light_space_matrix(self.light_uniform.position).into();
+        self.queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&[self.light_uniform]));
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Mutable access to the scene graph. Changes are drawn from the next [`Renderer::update`].
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
  pub fn process_mouse_movement(&mut self, delta_x: f32, delta_y: f32) {
         self.camera_controller.process_mouse_movement(delta_x, delta_y);
     }
 
-pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-    let output = self.surface.get_current_texture()?;
-    let view = output
-        .texture
-        .create_view(&wgpu::TextureViewDescriptor::default());
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Mutable access to the camera. Changes reach the GPU on the next [`Renderer::update`].
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Points the camera at `node` and its descendants, far enough away to
+    /// see all of their meshes. Returns `false` if none of them has one.
+    pub fn frame_node(&mut self, node: NodeId) -> bool {
+        self.frame_nodes(vec![node])
+    }
+
+    /// Like [`Renderer::frame_node`] for the whole scene.
+    pub fn frame_scene(&mut self) -> bool {
+        self.frame_nodes(self.scene.roots().to_vec())
+    }
+
+    fn frame_nodes(&mut self, mut stack: Vec<NodeId>) -> bool {
+        self.scene.update_world_matrices();
+        let mut bounds: Option<Aabb> = None;
+        while let Some(id) = stack.pop() {
+            let Some(node) = self.scene.node(id) else {
+                continue;
+            };
+            stack.extend_from_slice(node.children());
+            let Some(mesh_bounds) = node.mesh.and_then(|mesh| self.mesh(mesh)?.bounds()) else {
+                continue;
+            };
+            let world_bounds = mesh_bounds.transform(&node.world_matrix());
+            bounds = Some(bounds.map_or(world_bounds, |bounds| bounds.union(&world_bounds)));
+        }
+        match bounds {
+            Some(bounds) => {
+                self.camera.frame(&bounds);
+                true
+            }
+            None => false,
+        }
+    }
 
+    pub fn action_map(&self) -> &ActionMap {
+        &self.action_map
+    }
+
+    /// Mutable access to the input bindings, for rebinding at runtime.
+    pub fn action_map_mut(&mut self) -> &mut ActionMap {
+        &mut self.action_map
+    }
+
+    pub fn set_action_map(&mut self, action_map: ActionMap) {
+        self.action_map = action_map;
+    }
+
+    pub fn camera_controller(&self) -> &CameraController {
+        &self.camera_controller
+    }
+
+    /// Mutable access to the movement speed, sensitivity and smoothing.
+    pub fn camera_controller_mut(&mut self) -> &mut CameraController {
+        &mut self.camera_controller
+    }
+
+pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
     let mut encoder = self
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
+    match &self.target {
+        RenderTarget::Surface(surface) => {
+            let output = surface.get_current_texture()?;
+            let view = output
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            self.draw(&mut encoder, &view);
+            self.queue.submit(std::iter::once(encoder.finish()));
+            output.present();
+        }
+        RenderTarget::Texture(texture) => {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.draw(&mut encoder, &view);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders the current scene and returns its pixels as tightly packed RGBA8
+/// rows, top row first.
+///
+/// Headless renderers draw into their own target; windowed renderers draw
+/// into a temporary texture matching the surface, so the frame on screen is
+/// left untouched.
+pub fn read_pixels(&self) -> Result<Vec<u8>, ReadbackError> {
+    let capture_texture;
+    let texture = match &self.target {
+        RenderTarget::Texture(texture) => texture,
+        RenderTarget::Surface(_) => {
+            capture_texture = create_target_texture(&self.device, &self.config);
+            &capture_texture
+        }
+    };
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut encoder = self
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Render Encoder"),
+        });
+    self.draw(&mut encoder, &view);
+    self.queue.submit(std::iter::once(encoder.finish()));
+
+    readback::read_rgba8(&self.device, &self.queue, texture)
+}
+
+/// Renders the current scene and writes it to `path` as a PNG file.
+pub fn capture_frame(&self, path: impl AsRef<std::path::Path>) -> Result<(), CaptureError> {
+    let pixels = self.read_pixels()?;
+    screenshot::write_png(path, self.config.width, self.config.height, &pixels)
+}
+
+/// Records the shadow pass, the scene's render pass into the HDR target,
+/// followed by the sky, and the tonemapping pass into `encoder`, drawing
+/// into `view`.
+fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+    let draws = self
+        .draw_list
+        .iter()
+        .filter_map(|&(mesh, slot)| Some((self.mesh(mesh)?, self.transforms.offset(slot))));
+    self.shadow_map.render(encoder, &self.transforms, draws);
+
     {
+        // With MSAA, draw into the multisampled target and resolve into the
+        // HDR target; the samples themselves are not needed afterwards.
+        let hdr_view = self.tonemapper.view();
+        let (color_view, resolve_target) = match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(hdr_view)),
+            None => (hdr_view, None),
+        };
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
+                view: color_view,
+                resolve_target,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
                         r: 0.1,
@@ -613,13 +1148,13 @@ pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
                         b: 0.3,
                         a: 1.0,
                     }),
-                    store: true,
+                    store: resolve_target.is_none(),
                 },
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &self.depth_view,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Clear(if self.reverse_z { 0.0 } else { 1.0 }),
                     store: true,
                 }),
                 stencil_ops: None,
@@ -630,49 +1165,232 @@ pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
         render_pass.set_bind_group(2, &self.light_bind_group, &[]);
 
-        // First render pyramid with rotation and translation
-        let model = Matrix4::from_angle_x(Deg(self.rotation * 0.7)) * 
-                   Matrix4::from_angle_y(Deg(self.rotation)) *
-                   Matrix4::from_angle_z(Deg(self.rotation * 0.3)) *
-                   Matrix4::from_translation(Vector3::new(0.0, 1.0, 0.0));  // Lift pyramid up
-        
-        let transform_uniform = TransformUniform {
-            model: model.into(),
-        };
-        self.queue.write_buffer(
-            &self.transform_buffer,
-            0,
-            bytemuck::cast_slice(&[transform_uniform]),
-        );
-        render_pass.set_bind_group(1, &self.transform_bind_group, &[]);
-        
-        // Render pyramid vertices first
-        let pyramid_vertex_count = 18;
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.draw(0..pyramid_vertex_count, 0..1);
-        
-        // Then render ground plane with identity transform
-        let ground_transform_uniform = TransformUniform {
-            model: Matrix4::identity().into(),
-        };
-        self.queue.write_buffer(
-            &self.transform_buffer,
-            0,
-            bytemuck::cast_slice(&[ground_transform_uniform]),
-        );
-        render_pass.set_bind_group(1, &self.transform_bind_group, &[]);
-        
-        // Render ground plane vertices
-        let ground_start_index = 18;  // Start index for ground vertices
-        let ground_vertex_count = 18;
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.draw(ground_start_index..ground_start_index + ground_vertex_count, 0..1);
+        for &(mesh, slot) in &self.draw_list {
+            let Some(mesh) = self.mesh(mesh) else {
+                continue;
+            };
+            render_pass.set_bind_group(1, self.transforms.bind_group(), &[self.transforms.offset(slot)]);
+            render_pass.set_bind_group(3, self.material_bind_group(mesh), &[]);
+            mesh.draw(&mut render_pass);
+        }
+
+        self.skybox.draw(&mut render_pass);
     }
 
-    self.queue.submit(std::iter::once(encoder.finish()));
-    output.present();
+    self.tonemapper.render(encoder, view);
+}
 
-    Ok(())
 }
 
+/// Builds the main scene pipeline from `shader`'s `vs_main` and `fs_main`,
+/// drawing into the HDR target.
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    reverse_z: bool,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: HDR_FORMAT,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: if reverse_z {
+                wgpu::CompareFunction::Greater
+            } else {
+                wgpu::CompareFunction::Less
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+/// Binds the main light uniform together with the shadow map, its comparison
+/// sampler, the dynamic lights and the environment's lighting.
+fn create_light_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    light_buffer: &wgpu::Buffer,
+    shadow_map: &ShadowMap,
+    lights: &LightBuffer,
+    environment: &Environment,
+    environment_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Light Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(shadow_map.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(shadow_map.sampler()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: lights.buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(environment.irradiance_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(environment.prefiltered_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(environment.brdf_lut_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::Sampler(environment_sampler),
+            },
+        ],
+    })
+}
+
+fn environment_texture_entry(binding: u32, view_dimension: wgpu::TextureViewDimension) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+/// The depth buffer, with the same sample count as the color target. It is
+/// only ever used as a render attachment.
+fn create_depth_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        // Not sampled: on the GL backend, a sampleable multisampled depth
+        // target stops the color resolve from writing anything.
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+/// The multisampled color target the scene is drawn into before resolving.
+fn create_msaa_view(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Color Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Sample counts out of 1, 2, 4 and 8 that `color_format` and the depth
+/// format both support as render targets.
+///
+/// Counts other than 4 need adapter-specific format features; without them
+/// only the counts WebGPU guarantees are reported.
+fn supported_sample_counts(adapter: &wgpu::Adapter, device: &wgpu::Device, color_format: wgpu::TextureFormat) -> Vec<u32> {
+    let format_features = |format: wgpu::TextureFormat| {
+        if device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(device.features())
+        }
+    };
+    let color = format_features(color_format).flags;
+    let depth = format_features(DEPTH_FORMAT).flags;
+    [1, 2, 4, 8]
+        .into_iter()
+        .filter(|&count| count == 1 || (color.sample_count_supported(count) && depth.sample_count_supported(count)))
+        .collect()
+}
+
+/// Creates a texture the pipeline can draw into and that can be copied out
+/// for readback.
+fn create_target_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Target Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
 }
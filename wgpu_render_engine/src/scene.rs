@@ -0,0 +1,257 @@
+//! A scene graph of nodes with local transforms and cached world matrices.
+
+use cgmath::{Matrix4, One, Quaternion, SquareMatrix, Vector3};
+
+use crate::mesh::MeshId;
+use crate::slot::{SlotKey, Slots};
+
+/// A local translation, rotation and scale, applied in scale-rotate-translate order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self {
+            translation,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_rotation(self, rotation: Quaternion<f32>) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn with_scale(self, scale: Vector3<f32>) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+/// Handle to a node in a [`Scene`]. Once the node is removed the handle
+/// never refers to anything again, even after a new node takes its place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(SlotKey);
+
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub mesh: Option<MeshId>,
+    local: Transform,
+    world: Matrix4<f32>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// Set when `local` changed since `world` was last computed.
+    dirty: bool,
+}
+
+impl Node {
+    pub fn local_transform(&self) -> &Transform {
+        &self.local
+    }
+
+    /// The node's world matrix as of the last [`Scene::update_world_matrices`].
+    pub fn world_matrix(&self) -> Matrix4<f32> {
+        self.world
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A hierarchy of nodes, each with an optional mesh.
+///
+/// Changing a node's local transform marks it dirty; the next
+/// [`Scene::update_world_matrices`] recomputes the world matrices of dirty
+/// nodes and their descendants only.
+#[derive(Debug, Default)]
+pub struct Scene {
+    nodes: Slots<Node>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node under `parent`, or as a root when `parent` is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` refers to a removed node.
+    pub fn add_node(
+        &mut self,
+        parent: Option<NodeId>,
+        transform: Transform,
+        mesh: Option<MeshId>,
+    ) -> NodeId {
+        if let Some(parent) = parent {
+            assert!(self.node(parent).is_some(), "parent node was removed");
+        }
+        let node = Node {
+            name: String::new(),
+            mesh,
+            local: transform,
+            world: Matrix4::identity(),
+            parent,
+            children: Vec::new(),
+            dirty: true,
+        };
+        let id = NodeId(self.nodes.insert(node));
+        match parent {
+            Some(parent) => self.nodes.get_mut(parent.0).unwrap().children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// Removes `id` and all of its descendants.
+    pub fn remove_node(&mut self, id: NodeId) {
+        let Some(node) = self.nodes.remove(id.0) else {
+            return;
+        };
+        self.detach(id, node.parent);
+        for child in node.children {
+            self.remove_subtree(child);
+        }
+    }
+
+    fn remove_subtree(&mut self, id: NodeId) {
+        if let Some(node) = self.nodes.remove(id.0) {
+            for child in node.children {
+                self.remove_subtree(child);
+            }
+        }
+    }
+
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let siblings = match parent {
+            Some(parent) => match self.node_mut(parent) {
+                Some(parent) => &mut parent.children,
+                None => return,
+            },
+            None => &mut self.roots,
+        };
+        siblings.retain(|&sibling| sibling != id);
+    }
+
+    /// Moves `id` under `parent`, keeping its local transform.
+    ///
+    /// Returns `false` and leaves the scene unchanged if either node is
+    /// missing or `parent` is `id` itself or one of its descendants.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        let Some(old_parent) = self.node(id).map(|node| node.parent) else {
+            return false;
+        };
+        if let Some(parent) = parent {
+            if self.node(parent).is_none() || self.is_ancestor_or_self(id, parent) {
+                return false;
+            }
+        }
+        self.detach(id, old_parent);
+        match parent {
+            Some(parent) => self.nodes.get_mut(parent.0).unwrap().children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.nodes.get_mut(id.0).unwrap();
+        node.parent = parent;
+        node.dirty = true;
+        true
+    }
+
+    fn is_ancestor_or_self(&self, ancestor: NodeId, mut id: NodeId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
+            match self.node(id).and_then(|node| node.parent) {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)
+    }
+
+    /// Mutable access to a node's name and mesh. Use
+    /// [`Scene::set_local_transform`] to move it.
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0)
+    }
+
+    pub fn set_local_transform(&mut self, id: NodeId, transform: Transform) {
+        if let Some(node) = self.node_mut(id) {
+            node.local = transform;
+            node.dirty = true;
+        }
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Iterates over every live node.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes.iter().map(|(key, node)| (NodeId(key), node))
+    }
+
+    /// Finds the first node with the given name.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes().find(|(_, node)| node.name == name).map(|(id, _)| id)
+    }
+
+    /// Recomputes the world matrix of every dirty node and its descendants.
+    pub fn update_world_matrices(&mut self) {
+        for index in 0..self.roots.len() {
+            let root = self.roots[index];
+            self.update_subtree(root, Matrix4::identity(), false);
+        }
+    }
+
+    fn update_subtree(&mut self, id: NodeId, parent_world: Matrix4<f32>, parent_changed: bool) {
+        let Some(node) = self.nodes.get_mut(id.0) else {
+            return;
+        };
+        let changed = parent_changed || node.dirty;
+        if changed {
+            node.world = parent_world * node.local.matrix();
+            node.dirty = false;
+        }
+        let world = node.world;
+        for index in 0..node.children.len() {
+            let child = self.nodes.get(id.0).unwrap().children[index];
+            self.update_subtree(child, world, changed);
+        }
+    }
+
+    /// Iterates over every node with a mesh, with its cached world matrix.
+    pub fn drawables(&self) -> impl Iterator<Item = (MeshId, Matrix4<f32>)> + '_ {
+        self.nodes.values().filter_map(|node| Some((node.mesh?, node.world)))
+    }
+}
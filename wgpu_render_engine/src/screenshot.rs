@@ -0,0 +1,76 @@
+//! Saving rendered frames as PNG files.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::readback::ReadbackError;
+
+/// Errors raised while capturing a frame to disk.
+#[derive(Debug)]
+pub enum CaptureError {
+    /// The frame could not be copied back from the GPU.
+    Readback(ReadbackError),
+    /// The output file could not be created or written.
+    Io(std::io::Error),
+    /// The pixels could not be encoded as PNG.
+    Encode(png::EncodingError),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::Readback(e) => write!(f, "failed to read back frame: {}", e),
+            CaptureError::Io(e) => write!(f, "failed to write screenshot: {}", e),
+            CaptureError::Encode(e) => write!(f, "failed to encode PNG: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<ReadbackError> for CaptureError {
+    fn from(e: ReadbackError) -> Self {
+        CaptureError::Readback(e)
+    }
+}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(e: std::io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for CaptureError {
+    fn from(e: png::EncodingError) -> Self {
+        CaptureError::Encode(e)
+    }
+}
+
+/// Writes tightly packed, sRGB-encoded RGBA8 pixels to `path` as a PNG file.
+pub fn write_png(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> Result<(), CaptureError> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Returns a `screenshot-<unix millis>.png` path in the working directory.
+pub fn timestamped_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    PathBuf::from(format!("screenshot-{}.png", millis))
+}
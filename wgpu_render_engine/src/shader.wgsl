@@ -4,6 +4,7 @@ struct CameraUniform {
 }
 struct TransformUniform { 
     model: mat4x4<f32>, 
+    normal: mat3x3<f32>,
 }
 struct LightUniform { 
     position: vec3<f32>, 
@@ -11,36 +12,206 @@ struct LightUniform {
     ambient: f32, 
     diffuse: f32, 
     specular: f32, 
-    light_space_matrix: mat4x4<f32>, 
+    light_space_matrix: mat4x4<f32>,
+    environment_intensity: f32,
+    max_reflection_lod: f32,
+    has_environment: u32,
+}
+// A dynamic light; `kind` is 0 for point, 1 for spot and 2 for directional.
+struct GpuLight {
+    position: vec3<f32>,
+    kind: u32,
+    direction: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    inner_cos: f32,
+    outer_cos: f32,
+}
+struct LightArray {
+    count: u32,
+    lights: array<GpuLight>,
+}
+// `grid_scale` is zero when the material has no grid. `shading` is 0 for
+// Blinn-Phong and 1 for metallic-roughness.
+struct MaterialUniform {
+    base_color: vec3<f32>,
+    specular_strength: f32,
+    grid_line_color: vec3<f32>,
+    shininess: f32,
+    grid_scale: f32,
+    shading: u32,
+    metallic: f32,
+    roughness: f32,
 }
 struct VertexInput { 
     @location(0) position: vec3<f32>, 
     @location(1) color: vec3<f32>, 
     @location(2) normal: vec3<f32>, 
+    @location(3) tex_coords: vec2<f32>,
 }
 struct VertexOutput { 
     @builtin(position) clip_position: vec4<f32>, 
     @location(0) world_position: vec3<f32>, 
     @location(1) world_normal: vec3<f32>, 
     @location(2) color: vec3<f32>, 
+    @location(3) tex_coords: vec2<f32>,
 }
 @group(0) @binding(0) var<uniform> camera: CameraUniform;
 @group(1) @binding(0) var<uniform> transform: TransformUniform;
 @group(2) @binding(0) var<uniform> light: LightUniform;
+@group(2) @binding(1) var shadow_map: texture_depth_2d;
+@group(2) @binding(2) var shadow_sampler: sampler_comparison;
+@group(2) @binding(3) var<storage, read> dynamic_lights: LightArray;
+@group(2) @binding(4) var irradiance_map: texture_cube<f32>;
+@group(2) @binding(5) var prefiltered_map: texture_cube<f32>;
+@group(2) @binding(6) var brdf_lut: texture_2d<f32>;
+@group(2) @binding(7) var environment_sampler: sampler;
+@group(3) @binding(0) var<uniform> material: MaterialUniform;
+@group(3) @binding(1) var base_texture: texture_2d<f32>;
+@group(3) @binding(2) var base_sampler: sampler;
 
-fn simple_ground_texture(pos: vec3<f32>) -> vec3<f32> {
-    // Create a grid-like pattern for the ground
-    let grid_scale = 2.0;
-    let x = abs(fract(pos.x * grid_scale) - 0.5);
-    let z = abs(fract(pos.z * grid_scale) - 0.5);
-    
-    // Base ground color
-    let base_green = vec3<f32>(0.2, 0.5, 0.2);
-    let dark_green = vec3<f32>(0.15, 0.4, 0.15);
+const PI: f32 = 3.14159265359;
+
+fn grid_texture(pos: vec3<f32>) -> vec3<f32> {
+    // Create a grid-like pattern on the XZ plane
+    let x = abs(fract(pos.x * material.grid_scale) - 0.5);
+    let z = abs(fract(pos.z * material.grid_scale) - 0.5);
     
     // Create a subtle grid effect
     let grid_intensity = smoothstep(0.45, 0.5, max(x, z));
-    return mix(base_green, dark_green, grid_intensity);
+    return mix(material.base_color, material.grid_line_color, grid_intensity);
+}
+
+// Fraction of light reaching `world_position`, averaged over a 3x3 PCF kernel.
+// Points outside the shadow map are fully lit.
+fn shadow_factor(world_position: vec3<f32>) -> f32 {
+    let light_clip = light.light_space_matrix * vec4<f32>(world_position, 1.0);
+    let ndc = light_clip.xyz / light_clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, ndc.z);
+        }
+    }
+    return lit / 9.0;
+}
+
+// GGX / Trowbridge-Reitz normal distribution, with alpha = roughness^2.
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith masking-shadowing with the Schlick-GGX approximation for direct light.
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Reflectance at normal incidence: 4% for dielectrics, the base color for metals.
+fn base_reflectance(base_color: vec3<f32>) -> vec3<f32> {
+    return mix(vec3<f32>(0.04), base_color, material.metallic);
+}
+
+// Cook-Torrance reflection of light arriving from `light_dir`. `irradiance`
+// is the light's color at the surface when facing it head-on, so a white
+// light on a white matte surface gives white. Light the specular lobe does
+// not reflect is left for the diffuse lobe, and metals have no diffuse lobe,
+// so no light is reflected twice.
+fn cook_torrance(normal: vec3<f32>, view_dir: vec3<f32>, light_dir: vec3<f32>, irradiance: vec3<f32>, base_color: vec3<f32>) -> vec3<f32> {
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    let n_dot_v = max(dot(normal, view_dir), 0.0001);
+    let halfway_dir = normalize(light_dir + view_dir);
+    // Very low roughness turns point lights into invisible specks.
+    let roughness = clamp(material.roughness, 0.045, 1.0);
+
+    let fresnel = fresnel_schlick(max(dot(halfway_dir, view_dir), 0.0), base_reflectance(base_color));
+    let d = distribution_ggx(max(dot(normal, halfway_dir), 0.0), roughness);
+    let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+    let specular = d * g * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+
+    let k_d = (vec3<f32>(1.0) - fresnel) * (1.0 - material.metallic);
+    return (k_d * base_color / PI + specular) * irradiance * PI * n_dot_l;
+}
+
+// Light arriving at a surface facing `normal` from everything but the light
+// sources: the environment's irradiance if one is set, else a flat term.
+fn ambient_light(normal: vec3<f32>) -> vec3<f32> {
+    if (light.has_environment != 0u) {
+        return textureSampleLevel(irradiance_map, environment_sampler, normal, 0.0).rgb * light.environment_intensity;
+    }
+    return light.color * light.ambient;
+}
+
+// Ambient light reflected by a metallic-roughness surface, split between the
+// diffuse and specular lobes the same way as direct light. With an
+// environment, the specular lobe reflects it using the split-sum
+// approximation: prefiltered radiance times a scale and bias to f0.
+fn ambient_pbr(normal: vec3<f32>, view_dir: vec3<f32>, base_color: vec3<f32>) -> vec3<f32> {
+    let n_dot_v = max(dot(normal, view_dir), 0.0);
+    let f0 = base_reflectance(base_color);
+    // Rough surfaces show less Fresnel brightening at grazing angles.
+    let f90 = max(vec3<f32>(1.0 - material.roughness), f0);
+    let k_s = f0 + (f90 - f0) * pow(1.0 - n_dot_v, 5.0);
+    let k_d = (vec3<f32>(1.0) - k_s) * (1.0 - material.metallic);
+    let ambient = ambient_light(normal);
+    if (light.has_environment == 0u) {
+        return (k_d * base_color + k_s) * ambient;
+    }
+
+    let reflected = reflect(-view_dir, normal);
+    let lod = material.roughness * light.max_reflection_lod;
+    let radiance = textureSampleLevel(prefiltered_map, environment_sampler, reflected, lod).rgb;
+    let brdf = textureSampleLevel(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, material.roughness), 0.0).rg;
+    let specular = radiance * light.environment_intensity * (f0 * brdf.x + brdf.y);
+    return k_d * base_color * ambient + specular;
+}
+
+// Contribution of a dynamic light, including distance and cone falloff.
+fn dynamic_light(l: GpuLight, position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, base_color: vec3<f32>) -> vec3<f32> {
+    var light_dir = -l.direction;
+    var attenuation = 1.0;
+    if (l.kind != 2u) {
+        let to_light = l.position - position;
+        let distance = length(to_light);
+        light_dir = to_light / max(distance, 0.0001);
+        attenuation = 1.0 / (1.0 + distance * distance);
+        if (l.range > 0.0) {
+            // Smooth window so the light reaches exactly zero at its range.
+            let ratio = distance / l.range;
+            attenuation *= pow(clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0), 2.0);
+        }
+        if (l.kind == 1u) {
+            let cos_angle = dot(-light_dir, l.direction);
+            attenuation *= smoothstep(l.outer_cos, l.inner_cos, cos_angle);
+        }
+    }
+
+    let radiance = l.color * l.intensity * attenuation;
+    if (material.shading == 1u) {
+        return cook_torrance(normal, view_dir, light_dir, radiance, base_color);
+    }
+    let diff = max(dot(normal, light_dir), 0.0);
+    let halfway_dir = normalize(light_dir + view_dir);
+    let spec = pow(max(dot(normal, halfway_dir), 0.0), material.shininess);
+    return radiance * (base_color * diff * light.diffuse + spec * light.specular * material.specular_strength);
 }
 
 @vertex fn vs_main(model: VertexInput) -> VertexOutput { 
@@ -50,15 +221,11 @@ fn simple_ground_texture(pos: vec3<f32>) -> vec3<f32> {
     
     out.world_position = world_position.xyz;
     
-    let normal_matrix = mat3x3<f32>(
-        transform.model[0].xyz,
-        transform.model[1].xyz,
-        transform.model[2].xyz
-    );
-    out.world_normal = normalize(normal_matrix * model.normal);
+    out.world_normal = normalize(transform.normal * model.normal);
     
     out.clip_position = camera.view_proj * vec4<f32>(out.world_position, 1.0); 
     out.color = model.color;
+    out.tex_coords = model.tex_coords;
     
     return out; 
 } 
@@ -68,30 +235,39 @@ fn simple_ground_texture(pos: vec3<f32>) -> vec3<f32> {
     let light_dir = normalize(light.position - in.world_position);
     let view_dir = normalize(camera.view_position - in.world_position);
     
-    // Determine if this is a ground vertex based on vertex color
-    let is_ground = in.color.g > 0.4 && in.color.r < 0.3;  // Ground vertices have more green
-    
-    // Choose base color based on whether it's ground or pyramid
-    let base_color = select(
-        in.color,  // Use vertex color for pyramid
-        simple_ground_texture(in.world_position),  // Use ground texture for ground
-        is_ground
-    );
+    // Grid materials replace the vertex color with their pattern
+    var base_color = in.color * material.base_color;
+    if (material.grid_scale > 0.0) {
+        base_color = grid_texture(in.world_position);
+    }
+    base_color *= textureSample(base_texture, base_sampler, in.tex_coords).rgb;
     
     // Ambient term
-    let ambient = light.color * light.ambient;
-    
-    // Diffuse term with enhanced visibility
-    let diff = max(dot(normal, light_dir), 0.3);
-    let diffuse = light.color * diff * light.diffuse;
-    
-    // Specular term (reduced for ground)
-    let halfway_dir = normalize(light_dir + view_dir);
-    let spec = pow(max(dot(normal, halfway_dir), 0.0), 32.0);
-    let specular = light.color * spec * light.specular * select(1.0, 0.2, is_ground);
-    
-    // Combine lighting terms
-    let final_color = base_color * (ambient + diffuse) + specular;
+    let ambient = ambient_light(normal);
+    let shadow = shadow_factor(in.world_position);
+    var final_color: vec3<f32>;
+
+    if (material.shading == 1u) {
+        // The light's diffuse and specular scales only apply to Blinn-Phong
+        let direct = cook_torrance(normal, view_dir, light_dir, light.color, base_color);
+        final_color = ambient_pbr(normal, view_dir, base_color) + direct * shadow;
+    } else {
+        // Diffuse term with enhanced visibility
+        let diff = max(dot(normal, light_dir), 0.3);
+        let diffuse = light.color * diff * light.diffuse;
+
+        // Specular term, scaled by the material
+        let halfway_dir = normalize(light_dir + view_dir);
+        let spec = pow(max(dot(normal, halfway_dir), 0.0), material.shininess);
+        let specular = light.color * spec * light.specular * material.specular_strength;
+
+        // Combine lighting terms; shadowed fragments keep only the ambient term
+        final_color = base_color * (ambient + diffuse * shadow) + specular * shadow;
+    }
+
+    for (var i = 0u; i < dynamic_lights.count; i++) {
+        final_color += dynamic_light(dynamic_lights.lights[i], in.world_position, normal, view_dir, base_color);
+    }
     
     return vec4<f32>(final_color, 1.0);
 }
@@ -0,0 +1,268 @@
+//! Shadow mapping for the scene light.
+//!
+//! Each frame the scene is first rendered depth-only from the light into a
+//! [`ShadowMap`]. The main pass then projects every fragment into light space
+//! and compares its depth against the map through a comparison sampler,
+//! averaging a 3x3 neighbourhood (PCF) for soft edges.
+
+use cgmath::{ortho, InnerSpace, Matrix4, Point3, Vector3};
+
+use crate::mesh::Mesh;
+use crate::transform::TransformBuffer;
+use crate::vertex::Vertex;
+
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Converts cgmath's OpenGL clip space (z in -1..1) to wgpu's (z in 0..1).
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// How the shadow map is rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// Width and height of the shadow map in texels.
+    pub map_size: u32,
+    /// Constant depth bias added in the shadow pass, in depth buffer units.
+    pub depth_bias: i32,
+    /// Depth bias scaled by the slope of each triangle as seen from the light.
+    pub slope_scale_bias: f32,
+    /// Half-size of the square area around `target` covered by the map.
+    pub extent: f32,
+    /// Point the light looks at.
+    pub target: Point3<f32>,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            map_size: 2048,
+            depth_bias: 2,
+            slope_scale_bias: 2.0,
+            extent: 20.0,
+            target: Point3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl ShadowSettings {
+    /// The orthographic view-projection from a light at `position` onto the
+    /// area around [`ShadowSettings::target`], in wgpu clip space.
+    pub fn light_space_matrix(&self, position: [f32; 3]) -> Matrix4<f32> {
+        let eye = Point3::from(position);
+        let mut forward = self.target - eye;
+        if forward.magnitude2() == 0.0 {
+            forward = -Vector3::unit_y();
+        }
+        let up = if forward.normalize().y.abs() > 0.99 {
+            Vector3::unit_z()
+        } else {
+            Vector3::unit_y()
+        };
+        let view = Matrix4::look_to_rh(eye, forward, up);
+        // Deep enough to catch casters behind the target as well as in front.
+        let far = forward.magnitude() + 2.0 * self.extent;
+        let proj = ortho(-self.extent, self.extent, -self.extent, self.extent, 0.1, far);
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+}
+
+/// The depth texture rendered from the light and the pipeline that fills it.
+pub struct ShadowMap {
+    settings: ShadowSettings,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    /// The light buffer alone, so the pass does not bind the map it renders to.
+    light_bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl ShadowMap {
+    /// Creates the shadow map. The depth pass reads `light_space_matrix` from
+    /// `light_buffer` and model matrices through `transform_layout`.
+    pub fn new(
+        device: &wgpu::Device,
+        settings: ShadowSettings,
+        light_buffer: &wgpu::Buffer,
+        transform_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let (texture, view) = Self::create_texture(device, settings.map_size);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("shadow.wgsl"))),
+        });
+        let light_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Light Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Light Bind Group"),
+            layout: &light_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&light_layout, transform_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, &settings);
+
+        Self {
+            settings,
+            texture,
+            view,
+            sampler,
+            light_bind_group,
+            pipeline_layout,
+            shader,
+            pipeline,
+        }
+    }
+
+    fn create_texture(device: &wgpu::Device, size: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        settings: &ShadowSettings,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_shadow",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: settings.depth_bias,
+                    slope_scale: settings.slope_scale_bias,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    /// Applies new settings, recreating the texture if the size changed and
+    /// the pipeline if the bias changed. Returns `true` if the texture was
+    /// recreated, in which case bind groups referring to [`ShadowMap::view`]
+    /// must be rebuilt.
+    pub fn set_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) -> bool {
+        let old = std::mem::replace(&mut self.settings, settings);
+        if old.depth_bias != settings.depth_bias || old.slope_scale_bias != settings.slope_scale_bias {
+            self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, &settings);
+        }
+        if old.map_size != settings.map_size {
+            (self.texture, self.view) = Self::create_texture(device, settings.map_size);
+            return true;
+        }
+        false
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// The comparison sampler used to filter the map.
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// Records the depth pass. `draws` yields each mesh with its dynamic
+    /// offset into `transforms`.
+    pub fn render<'a>(
+        &'a self,
+        encoder: &mut wgpu::CommandEncoder,
+        transforms: &'a TransformBuffer,
+        draws: impl Iterator<Item = (&'a Mesh, wgpu::DynamicOffset)>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.light_bind_group, &[]);
+        for (mesh, offset) in draws {
+            render_pass.set_bind_group(1, transforms.bind_group(), &[offset]);
+            mesh.draw(&mut render_pass);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+struct TransformUniform {
+    model: mat4x4<f32>,
+    normal: mat3x3<f32>,
+}
+struct LightUniform {
+    position: vec3<f32>,
+    color: vec3<f32>,
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    light_space_matrix: mat4x4<f32>,
+    environment_intensity: f32,
+    max_reflection_lod: f32,
+    has_environment: u32,
+}
+@group(0) @binding(0) var<uniform> light: LightUniform;
+@group(1) @binding(0) var<uniform> transform: TransformUniform;
+
+// Depth-only pass from the light; the rasterizer writes the depth.
+@vertex fn vs_shadow(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return light.light_space_matrix * transform.model * vec4<f32>(position, 1.0);
+}
@@ -0,0 +1,428 @@
+//! The sky drawn behind the scene.
+//!
+//! After the opaque geometry, a single triangle at the far plane covers the
+//! target. The depth test passes only where nothing was drawn, and each pixel
+//! is colored by its view direction, using the camera's rotation but not its
+//! position, so the sky stays infinitely far away.
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+
+use crate::camera::Camera;
+use crate::environment::{Environment, EnvironmentData};
+use crate::renderer::DEPTH_FORMAT;
+use crate::tonemap::HDR_FORMAT;
+
+/// What the skybox draws behind the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sky {
+    /// Nothing; the background keeps the clear color.
+    #[default]
+    None,
+    /// The cubemap of the environment set with
+    /// [`Renderer::set_environment`], scaled like its lighting. Nothing is
+    /// drawn while none is set.
+    ///
+    /// [`Renderer::set_environment`]: crate::Renderer::set_environment
+    Environment,
+    /// The equirectangular image uploaded with [`Renderer::set_sky_image`].
+    ///
+    /// [`Renderer::set_sky_image`]: crate::Renderer::set_sky_image
+    Image,
+    Procedural(ProceduralSky),
+}
+
+/// A sky computed from the view direction, with a sun disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProceduralSky {
+    /// Direction towards the sun; need not be normalized.
+    pub sun_direction: Vector3<f32>,
+    pub sun_color: [f32; 3],
+    pub sun_intensity: f32,
+    /// Angular radius of the sun disk, in degrees.
+    pub sun_angular_radius: f32,
+    /// Straight up, for the gradient.
+    pub zenith_color: [f32; 3],
+    /// At the horizon, for the gradient.
+    pub horizon_color: [f32; 3],
+    /// Below the horizon.
+    pub ground_color: [f32; 3],
+    /// `None` for a gradient from the horizon to the zenith.
+    pub atmosphere: Option<Atmosphere>,
+}
+
+impl Default for ProceduralSky {
+    /// A blue gradient, with the sun in front of and above the default camera.
+    fn default() -> Self {
+        Self {
+            sun_direction: Vector3::new(0.3, 0.4, -1.0),
+            sun_color: [1.0, 0.95, 0.85],
+            sun_intensity: 20.0,
+            sun_angular_radius: 0.5,
+            zenith_color: [0.15, 0.35, 0.8],
+            horizon_color: [0.6, 0.75, 0.9],
+            ground_color: [0.25, 0.22, 0.2],
+            atmosphere: None,
+        }
+    }
+}
+
+/// Sunlight scattered by the air and haze of an Earth-like atmosphere, in
+/// place of the gradient. The sky darkens and reddens as the sun sets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Atmosphere {
+    /// Multiplies the density of air, which scatters blue light the most.
+    pub rayleigh: f32,
+    /// Multiplies the density of haze, which brightens the sky around the sun.
+    pub mie: f32,
+    /// How strongly haze scatters forwards, from -1 to 1.
+    pub mie_anisotropy: f32,
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            rayleigh: 1.0,
+            mie: 1.0,
+            mie_anisotropy: 0.76,
+        }
+    }
+}
+
+crate::wgsl_struct! {
+    pub(crate) struct SkyUniform {
+        inverse_view_proj: [[f32; 4]; 4],
+        sun_direction: [f32; 3],
+        sun_cos_radius: f32,
+        sun_radiance: [f32; 3],
+        mode: u32,
+        zenith_color: [f32; 3],
+        far_depth: f32,
+        horizon_color: [f32; 3],
+        environment_intensity: f32,
+        ground_color: [f32; 3],
+        atmosphere: u32,
+        rayleigh: f32,
+        mie: f32,
+        mie_anisotropy: f32,
+    }
+}
+
+impl SkyUniform {
+    fn from_sky(sky: &Sky, camera: &Camera, environment_intensity: f32) -> Self {
+        let inverse_view_proj = camera
+            .rotation_projection_matrix()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        let far_depth = if camera.projection().reverse_z() { 0.0 } else { 1.0 };
+        let (mode, procedural) = match sky {
+            Sky::None | Sky::Environment => (0, ProceduralSky::default()),
+            Sky::Image => (1, ProceduralSky::default()),
+            Sky::Procedural(procedural) => (2, *procedural),
+        };
+        let atmosphere = procedural.atmosphere.unwrap_or_default();
+        Self::new(
+            inverse_view_proj.into(),
+            procedural.sun_direction.normalize().into(),
+            procedural.sun_angular_radius.to_radians().cos(),
+            procedural.sun_color.map(|c| c * procedural.sun_intensity),
+            mode,
+            procedural.zenith_color,
+            far_depth,
+            procedural.horizon_color,
+            environment_intensity,
+            procedural.ground_color,
+            procedural.atmosphere.is_some() as u32,
+            atmosphere.rayleigh,
+            atmosphere.mie,
+            atmosphere.mie_anisotropy,
+        )
+    }
+}
+
+/// Draws a [`Sky`] into the scene's render pass.
+pub struct Skybox {
+    sky: Sky,
+    uniform_buffer: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    /// The uniform and the equirectangular image.
+    bind_group: wgpu::BindGroup,
+    /// A 1x1 texel until an image is uploaded.
+    image: wgpu::Texture,
+    has_image: bool,
+    environment_layout: wgpu::BindGroupLayout,
+    environment_bind_group: wgpu::BindGroup,
+    has_environment: bool,
+    /// Bound while no environment is set.
+    placeholder_environment: Environment,
+    sampler: wgpu::Sampler,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Skybox {
+    /// Creates a skybox drawing nothing, for a render pass with `sample_count`
+    /// samples whose depth is tested for a reverse-Z projection if `reverse_z`.
+    pub fn new(device: &wgpu::Device, reverse_z: bool, sample_count: u32) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sky Buffer"),
+            size: std::mem::size_of::<SkyUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sky Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1, wgpu::TextureViewDimension::D2, false),
+            ],
+        });
+        let environment_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sky Environment Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::Cube, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let image = create_image(device, 1, 1);
+        let bind_group = create_bind_group(device, &layout, &uniform_buffer, &image);
+        let placeholder_environment = Environment::placeholder(device);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sky Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let environment_bind_group =
+            create_environment_bind_group(device, &environment_layout, placeholder_environment.view(), &sampler);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sky Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("skybox.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky Pipeline Layout"),
+            bind_group_layouts: &[&layout, &environment_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(device, &pipeline_layout, &shader, reverse_z, sample_count);
+
+        Self {
+            sky: Sky::None,
+            uniform_buffer,
+            layout,
+            bind_group,
+            image,
+            has_image: false,
+            environment_layout,
+            environment_bind_group,
+            has_environment: false,
+            placeholder_environment,
+            sampler,
+            shader,
+            pipeline_layout,
+            pipeline,
+        }
+    }
+
+    pub fn sky(&self) -> &Sky {
+        &self.sky
+    }
+
+    /// Takes effect on the next [`Skybox::update`].
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = sky;
+    }
+
+    /// Uploads the image drawn by [`Sky::Image`], replacing any previous one,
+    /// downsampled to fit the device's texture size limit.
+    pub fn set_image(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &EnvironmentData) {
+        let data = data.fit_within(device.limits().max_texture_dimension_2d);
+        self.image = create_image(device, data.width, data.height);
+        queue.write_texture(
+            self.image.as_image_copy(),
+            bytemuck::cast_slice(&data.rgba),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(data.width * 16),
+                rows_per_image: Some(data.height),
+            },
+            self.image.size(),
+        );
+        self.bind_group = create_bind_group(device, &self.layout, &self.uniform_buffer, &self.image);
+        self.has_image = true;
+    }
+
+    /// Binds the environment drawn by [`Sky::Environment`], or nothing.
+    pub fn set_environment(&mut self, device: &wgpu::Device, environment: Option<&Environment>) {
+        let view = environment.unwrap_or(&self.placeholder_environment).view();
+        self.environment_bind_group =
+            create_environment_bind_group(device, &self.environment_layout, view, &self.sampler);
+        self.has_environment = environment.is_some();
+    }
+
+    /// Rebuilds the pipeline after the depth test or sample count of the
+    /// render pass changed.
+    pub fn rebuild_pipeline(&mut self, device: &wgpu::Device, reverse_z: bool, sample_count: u32) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, &self.shader, reverse_z, sample_count);
+    }
+
+    /// Uploads the sky and the camera's rotation for the next frame.
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera, environment_intensity: f32) {
+        let uniform = SkyUniform::from_sky(&self.sky, camera, environment_intensity);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Draws the sky into `render_pass` wherever its depth is still clear.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let visible = match self.sky {
+            Sky::None => false,
+            Sky::Environment => self.has_environment,
+            Sky::Image => self.has_image,
+            Sky::Procedural(_) => true,
+        };
+        if !visible {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.environment_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_image(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Sky Image"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    image: &wgpu::Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Sky Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(
+                    &image.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+        ],
+    })
+}
+
+fn create_environment_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Sky Environment Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+/// Builds the sky pipeline. It runs after the opaque geometry and tests
+/// against the far plane with `LessEqual`, or `GreaterEqual` under reverse-Z
+/// where the far plane is at depth 0, without writing depth.
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    reverse_z: bool,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Sky Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(HDR_FORMAT.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: if reverse_z {
+                wgpu::CompareFunction::GreaterEqual
+            } else {
+                wgpu::CompareFunction::LessEqual
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+fn texture_entry(binding: u32, view_dimension: wgpu::TextureViewDimension, filterable: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
@@ -0,0 +1,184 @@
+// Draws the sky behind the scene: one triangle at the far plane covering the
+// target, colored by the direction through each pixel. `mode` is 0 for the
+// environment cubemap, 1 for an equirectangular image and 2 for the
+// procedural sky. `sun_radiance` already includes the sun's intensity.
+struct SkyUniform {
+    inverse_view_proj: mat4x4<f32>,
+    sun_direction: vec3<f32>,
+    sun_cos_radius: f32,
+    sun_radiance: vec3<f32>,
+    mode: u32,
+    zenith_color: vec3<f32>,
+    far_depth: f32,
+    horizon_color: vec3<f32>,
+    environment_intensity: f32,
+    ground_color: vec3<f32>,
+    atmosphere: u32,
+    rayleigh: f32,
+    mie: f32,
+    mie_anisotropy: f32,
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+@group(0) @binding(0) var<uniform> sky: SkyUniform;
+@group(0) @binding(1) var image: texture_2d<f32>;
+@group(1) @binding(0) var environment: texture_cube<f32>;
+@group(1) @binding(1) var environment_sampler: sampler;
+
+const PI: f32 = 3.14159265359;
+
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.ndc = uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    out.clip_position = vec4<f32>(out.ndc, sky.far_depth, 1.0);
+    return out;
+}
+
+fn load_image(texel: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+    let wrapped = vec2<i32>((texel.x + size.x) % size.x, clamp(texel.y, 0, size.y - 1));
+    return textureLoad(image, wrapped, 0).rgb;
+}
+
+// 32-bit float textures are not filterable everywhere, so the image is
+// filtered by hand: wrapping around the horizon, clamped at the poles.
+fn sample_image(direction: vec3<f32>) -> vec3<f32> {
+    let uv = vec2<f32>(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+    let size = vec2<i32>(textureDimensions(image));
+    let position = uv * vec2<f32>(size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let t = fract(position);
+    let top = mix(load_image(base, size), load_image(base + vec2<i32>(1, 0), size), t.x);
+    let bottom = mix(load_image(base + vec2<i32>(0, 1), size), load_image(base + vec2<i32>(1, 1), size), t.x);
+    return mix(top, bottom, t.y);
+}
+
+// Zenith to horizon above the horizon, fading quickly into the ground below.
+fn gradient(direction: vec3<f32>) -> vec3<f32> {
+    if (direction.y >= 0.0) {
+        return mix(sky.horizon_color, sky.zenith_color, sqrt(direction.y));
+    }
+    return mix(sky.horizon_color, sky.ground_color, smoothstep(0.0, 0.05, -direction.y));
+}
+
+// Distances are in kilometers.
+const PLANET_RADIUS: f32 = 6371.0;
+const ATMOSPHERE_RADIUS: f32 = 6471.0;
+const RAYLEIGH_SCATTERING: vec3<f32> = vec3<f32>(5.8e-3, 13.5e-3, 33.1e-3);
+const MIE_SCATTERING: f32 = 21e-3;
+// Haze absorbs a little of the light it does not scatter.
+const MIE_EXTINCTION: f32 = 23.1e-3;
+const RAYLEIGH_HEIGHT: f32 = 8.0;
+const MIE_HEIGHT: f32 = 1.2;
+const VIEW_SAMPLES: u32 = 16u;
+const SUN_SAMPLES: u32 = 8u;
+
+// Distance from `origin` along `direction` to the edge of the atmosphere,
+// or to the ground if the ray hits it first and `stop_at_ground` is set.
+fn ray_length(origin: vec3<f32>, direction: vec3<f32>, stop_at_ground: bool) -> f32 {
+    let b = dot(origin, direction);
+    let ground = b * b - dot(origin, origin) + PLANET_RADIUS * PLANET_RADIUS;
+    if (stop_at_ground && b < 0.0 && ground > 0.0) {
+        return -b - sqrt(ground);
+    }
+    let c = dot(origin, origin) - ATMOSPHERE_RADIUS * ATMOSPHERE_RADIUS;
+    return -b + sqrt(max(b * b - c, 0.0));
+}
+
+// Density of air and of haze relative to sea level, at `position`.
+fn density(position: vec3<f32>) -> vec2<f32> {
+    let height = max(length(position) - PLANET_RADIUS, 0.0);
+    return exp(-height / vec2<f32>(RAYLEIGH_HEIGHT, MIE_HEIGHT));
+}
+
+// Fraction of light left after crossing `depth`, the air and haze densities
+// integrated along its path.
+fn transmittance(depth: vec2<f32>) -> vec3<f32> {
+    return exp(-(RAYLEIGH_SCATTERING * sky.rayleigh * depth.x + MIE_EXTINCTION * sky.mie * depth.y));
+}
+
+// Sunlight scattered towards the viewer by air and haze along `direction`,
+// marched from just above the ground: air scatters blue light the most,
+// haze brightens the sky around the sun. Also returns, in `w`, how much of
+// the sun's own light gets through.
+fn atmosphere(direction: vec3<f32>) -> vec4<f32> {
+    let origin = vec3<f32>(0.0, PLANET_RADIUS + 0.001, 0.0);
+    let total = ray_length(origin, direction, true);
+    var rayleigh = vec3<f32>(0.0);
+    var mie = vec3<f32>(0.0);
+    var view_depth = vec2<f32>(0.0);
+    for (var i = 0u; i < VIEW_SAMPLES; i++) {
+        // Samples spaced quadratically, densest near the ground where the
+        // air is thickest.
+        let start = f32(i) / f32(VIEW_SAMPLES);
+        let end = f32(i + 1u) / f32(VIEW_SAMPLES);
+        let step = (end * end - start * start) * total;
+        let middle = (start + end) * 0.5;
+        let position = origin + direction * middle * middle * total;
+        let local = density(position) * step;
+        view_depth += local;
+
+        // Points in the planet's shadow receive no sunlight.
+        let b = dot(position, sky.sun_direction);
+        if (b < 0.0 && b * b - dot(position, position) + PLANET_RADIUS * PLANET_RADIUS > 0.0) {
+            continue;
+        }
+        let sun_step = ray_length(position, sky.sun_direction, false) / f32(SUN_SAMPLES);
+        var sun_depth = vec2<f32>(0.0);
+        for (var j = 0u; j < SUN_SAMPLES; j++) {
+            sun_depth += density(position + sky.sun_direction * (f32(j) + 0.5) * sun_step) * sun_step;
+        }
+        let light = transmittance(view_depth + sun_depth);
+        rayleigh += local.x * light;
+        mie += local.y * light;
+    }
+
+    let mu = dot(direction, sky.sun_direction);
+    let rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    // Cornette-Shanks phase function: haze scatters mostly forwards.
+    let g = sky.mie_anisotropy;
+    let g2 = g * g;
+    let mie_phase = 3.0 / (8.0 * PI) * (1.0 - g2) * (1.0 + mu * mu) / ((2.0 + g2) * pow(1.0 + g2 - 2.0 * g * mu, 1.5));
+    let scattered = rayleigh * RAYLEIGH_SCATTERING * sky.rayleigh * rayleigh_phase + mie * MIE_SCATTERING * sky.mie * mie_phase;
+    let sun_visibility = dot(transmittance(view_depth), vec3<f32>(0.2126, 0.7152, 0.0722));
+    return vec4<f32>(sky.sun_radiance * scattered, sun_visibility);
+}
+
+fn procedural(direction: vec3<f32>) -> vec3<f32> {
+    var color: vec3<f32>;
+    var sun_visibility = 1.0;
+    if (sky.atmosphere != 0u) {
+        // Below the horizon the ground takes over, seen through the haze at the horizon.
+        let scattering = atmosphere(normalize(vec3<f32>(direction.x, max(direction.y, 0.0), direction.z)));
+        color = mix(scattering.rgb, sky.ground_color, smoothstep(0.0, 0.05, -direction.y));
+        sun_visibility = scattering.w;
+    } else {
+        color = gradient(direction);
+    }
+
+    // The sun disk, with a slightly soft edge, hidden by the ground.
+    let edge = (1.0 - sky.sun_cos_radius) * 0.2;
+    let disk = smoothstep(sky.sun_cos_radius - edge, sky.sun_cos_radius + edge, dot(direction, sky.sun_direction));
+    let above_ground = step(0.0, direction.y);
+    return color + sky.sun_radiance * disk * above_ground * sun_visibility;
+}
+
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let far = sky.inverse_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = normalize(far.xyz / far.w);
+    var color: vec3<f32>;
+    switch sky.mode {
+        case 0u: {
+            color = textureSampleLevel(environment, environment_sampler, direction, 0.0).rgb * sky.environment_intensity;
+        }
+        case 1u: {
+            color = sample_image(direction);
+        }
+        default: {
+            color = procedural(direction);
+        }
+    }
+    return vec4<f32>(color, 1.0);
+}
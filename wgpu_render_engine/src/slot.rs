@@ -0,0 +1,96 @@
+//! Generational slots behind the engine's handles.
+//!
+//! Removing a value frees its slot for the next insert but bumps the slot's
+//! generation, so a handle kept from before the removal stops matching
+//! instead of silently referring to whatever takes the slot next.
+
+/// Index and generation of a value stored in [`Slots`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct SlotKey {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Values addressed by [`SlotKey`], reusing the slots of removed values.
+#[derive(Debug)]
+pub(crate) struct Slots<T> {
+    slots: Vec<Slot<T>>,
+}
+
+impl<T> Default for Slots<T> {
+    fn default() -> Self {
+        Self { slots: Vec::new() }
+    }
+}
+
+impl<T> Slots<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value` in the first free slot and returns its key.
+    pub fn insert(&mut self, value: T) -> SlotKey {
+        let index = match self.slots.iter().position(|slot| slot.value.is_none()) {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+        let slot = &mut self.slots[index];
+        slot.value = Some(value);
+        SlotKey {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    /// Takes the value out and retires `key`, even if the slot is reused.
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let slot = self.slots.get_mut(key.index)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        Some(value)
+    }
+
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        let slot = self.slots.get(key.index)?;
+        slot.value.as_ref().filter(|_| slot.generation == key.generation)
+    }
+
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        let slot = self.slots.get_mut(key.index)?;
+        slot.value.as_mut().filter(|_| slot.generation == key.generation)
+    }
+
+    pub fn contains(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Iterates over every stored value with its key, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let key = SlotKey {
+                index,
+                generation: slot.generation,
+            };
+            Some((key, slot.value.as_ref()?))
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+}
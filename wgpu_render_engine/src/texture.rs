@@ -0,0 +1,363 @@
+//! Image textures with GPU-generated mipmaps.
+//!
+//! [`TextureData`] decodes PNG and JPEG files to RGBA8. Uploading one with
+//! [`Renderer::add_texture`] copies it into the top mip level and fills the
+//! rest by blitting each level into the next with a linear filter.
+//!
+//! [`Renderer::add_texture`]: crate::Renderer::add_texture
+
+use std::path::{Path, PathBuf};
+
+use crate::slot::SlotKey;
+
+/// Color textures are stored sRGB-encoded, so sampling returns linear values.
+pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Errors raised while loading a texture image.
+#[derive(Debug)]
+pub enum TextureError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: std::io::Error },
+    /// The image is not a PNG or JPEG, or is corrupt.
+    Decode(image::ImageError),
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            TextureError::Decode(e) => write!(f, "failed to decode image: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Io { source, .. } => Some(source),
+            TextureError::Decode(e) => Some(e),
+        }
+    }
+}
+
+/// An image on the CPU, ready to be uploaded with [`Renderer::add_texture`].
+///
+/// [`Renderer::add_texture`]: crate::Renderer::add_texture
+#[derive(Debug, Clone)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed sRGB RGBA8 rows, top row first.
+    pub rgba: Vec<u8>,
+}
+
+impl TextureData {
+    /// Loads a PNG or JPEG file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| TextureError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::decode(&bytes)
+    }
+
+    /// Decodes PNG or JPEG bytes, detecting the format from its signature.
+    pub fn decode(bytes: &[u8]) -> Result<Self, TextureError> {
+        let image = image::load_from_memory(bytes).map_err(TextureError::Decode)?.to_rgba8();
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+        })
+    }
+
+    /// Number of mip levels down to 1x1.
+    pub fn mip_level_count(&self) -> u32 {
+        32 - self.width.max(self.height).max(1).leading_zeros()
+    }
+}
+
+/// How a texture is filtered and wrapped when sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplerSettings {
+    /// Wrapping along the texture's width.
+    pub address_mode_u: wgpu::AddressMode,
+    /// Wrapping along the texture's height.
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    /// Filter between mip levels; `Nearest` picks the closest level.
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Maximum anisotropy, from 1 (off) to 16. Values above 1 require every
+    /// filter to be `Linear`.
+    pub anisotropy_clamp: u16,
+}
+
+impl Default for SamplerSettings {
+    /// Repeating, trilinear filtering.
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: 1,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Unfiltered texels, e.g. for pixel art.
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Self::default()
+        }
+    }
+
+    fn create_sampler(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: self.anisotropy_clamp,
+            ..Default::default()
+        })
+    }
+}
+
+/// Handle to a texture added with [`Renderer::add_texture`].
+/// Once the texture is removed the handle never refers to anything again, even
+/// after a new texture takes its place.
+///
+/// [`Renderer::add_texture`]: crate::Renderer::add_texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(pub(crate) SlotKey);
+
+/// A mipmapped texture on the GPU and the sampler it is drawn with.
+pub struct Texture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    sampler_settings: SamplerSettings,
+}
+
+impl Texture {
+    /// Uploads `data` and generates its mip chain with `mipmaps`.
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        data: &TextureData,
+        sampler_settings: SamplerSettings,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: data.width,
+            height: data.height,
+            depth_or_array_layers: 1,
+        };
+        let mip_level_count = data.mip_level_count();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            &data.rgba,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * data.width),
+                rows_per_image: Some(data.height),
+            },
+            size,
+        );
+        mipmaps.generate(device, queue, &texture, mip_level_count);
+
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler: sampler_settings.create_sampler(device),
+            texture,
+            sampler_settings,
+        }
+    }
+
+    /// A 1x1 white texture, sampled by materials without one of their own.
+    pub(crate) fn white(device: &wgpu::Device, queue: &wgpu::Queue, mipmaps: &MipmapGenerator) -> Self {
+        let data = TextureData {
+            width: 1,
+            height: 1,
+            rgba: vec![255; 4],
+        };
+        Self::new(device, queue, mipmaps, &data, SamplerSettings::default())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+
+    pub fn sampler_settings(&self) -> &SamplerSettings {
+        &self.sampler_settings
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+}
+
+/// Fills a texture's mip chain by drawing each level into the next.
+pub(crate) struct MipmapGenerator {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("blit.wgsl"))),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blit Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(TEXTURE_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            layout,
+            sampler,
+            pipeline,
+        }
+    }
+
+    /// Renders levels `1..mip_level_count` of `texture` from level 0.
+    pub fn generate(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        mip_level_count: u32,
+    ) {
+        if mip_level_count < 2 {
+            return;
+        }
+        let views: Vec<_> = (0..mip_level_count)
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Mip View"),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+        for pair in views.windows(2) {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Blit Bind Group"),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&pair[0]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Mipmap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &pair[1],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
@@ -0,0 +1,523 @@
+//! HDR rendering and tonemapping.
+//!
+//! The scene is drawn into an [`HDR_FORMAT`] texture, so lighting brighter
+//! than 1.0 is kept rather than clipped. A fullscreen pass then scales it by
+//! the exposure, compresses it into 0..1 with a [`ToneMapping`] operator,
+//! applies gamma and writes the result to the frame.
+//!
+//! With [`AutoExposure`], two compute passes first bin the frame's log
+//! luminance into a histogram and average it, and the exposure adapts
+//! smoothly towards the scene's brightness over time.
+
+use std::time::Duration;
+
+use wgpu::util::DeviceExt;
+
+/// Format of the target the scene is drawn into before tonemapping.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Bins of the luminance histogram; must match `exposure.wgsl`.
+const HISTOGRAM_BINS: u64 = 256;
+
+/// Width and height of the tile each histogram workgroup bins.
+const HISTOGRAM_TILE: u32 = 16;
+
+/// Curve that compresses HDR colors into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapping {
+    /// Clamps each channel to 1.0.
+    None,
+    /// `c / (1 + c)` per channel; never fully saturates.
+    Reinhard,
+    /// A fit of the ACES filmic curve, with a toe and a soft shoulder.
+    #[default]
+    Aces,
+}
+
+/// Exposure derived from the average luminance of each frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposure {
+    /// Darkest log2 luminance the histogram distinguishes.
+    pub min_log_luminance: f32,
+    /// Brightest log2 luminance the histogram distinguishes.
+    pub max_log_luminance: f32,
+    /// How quickly the exposure follows changes in brightness, per second.
+    pub adaptation_rate: f32,
+    /// Luminance the average is mapped to before [`TonemapSettings::exposure`].
+    pub middle_gray: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -8.0,
+            max_log_luminance: 4.0,
+            adaptation_rate: 1.5,
+            middle_gray: 0.18,
+        }
+    }
+}
+
+/// How the HDR frame is mapped to the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonemapSettings {
+    pub tone_mapping: ToneMapping,
+    /// Multiplier applied before tonemapping. With auto-exposure, it acts as
+    /// exposure compensation on top of the adapted value.
+    pub exposure: f32,
+    /// Display gamma. 2.2 matches the sRGB curve on sRGB outputs.
+    pub gamma: f32,
+    /// `None` for a fixed exposure.
+    pub auto_exposure: Option<AutoExposure>,
+}
+
+impl Default for TonemapSettings {
+    /// ACES at unit exposure and gamma 2.2, without auto-exposure.
+    fn default() -> Self {
+        Self {
+            tone_mapping: ToneMapping::Aces,
+            exposure: 1.0,
+            gamma: 2.2,
+            auto_exposure: None,
+        }
+    }
+}
+
+crate::wgsl_struct! {
+    pub(crate) struct TonemapUniform {
+        exposure: f32,
+        gamma_exponent: f32,
+        tone_mapping: u32,
+        auto_exposure: u32,
+        min_log_luminance: f32,
+        log_luminance_range: f32,
+        adaptation_rate: f32,
+        middle_gray: f32,
+        time_delta: f32,
+    }
+}
+
+impl TonemapUniform {
+    fn from_settings(settings: &TonemapSettings, output_format: wgpu::TextureFormat, time_delta: f32) -> Self {
+        // sRGB targets encode with roughly 1/2.2 on write, so only the
+        // remainder of the requested gamma is applied in the shader.
+        let gamma_exponent = if output_format.is_srgb() {
+            2.2 / settings.gamma
+        } else {
+            1.0 / settings.gamma
+        };
+        let tone_mapping = match settings.tone_mapping {
+            ToneMapping::None => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::Aces => 2,
+        };
+        let auto = settings.auto_exposure.unwrap_or_default();
+        Self::new(
+            settings.exposure,
+            gamma_exponent,
+            tone_mapping,
+            settings.auto_exposure.is_some() as u32,
+            auto.min_log_luminance,
+            auto.max_log_luminance - auto.min_log_luminance,
+            auto.adaptation_rate,
+            auto.middle_gray,
+            time_delta,
+        )
+    }
+}
+
+/// The histogram and pipelines behind auto-exposure.
+struct ExposurePasses {
+    layout: wgpu::BindGroupLayout,
+    histogram_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    histogram_pipeline: wgpu::ComputePipeline,
+    average_pipeline: wgpu::ComputePipeline,
+}
+
+/// The HDR target the scene is drawn into and the passes that map it to the output.
+pub struct Tonemapper {
+    settings: TonemapSettings,
+    output_format: wgpu::TextureFormat,
+    time_delta: f32,
+    uniform_buffer: wgpu::Buffer,
+    /// The adapted average luminance, written by the exposure passes.
+    luminance_buffer: wgpu::Buffer,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    /// `None` when the device cannot run compute shaders.
+    exposure: Option<ExposurePasses>,
+}
+
+impl Tonemapper {
+    /// Creates a `width` x `height` HDR target and a pass writing to
+    /// `output_format`. Auto-exposure needs compute shaders; without
+    /// `compute` it is unavailable.
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        output_format: wgpu::TextureFormat,
+        compute: bool,
+    ) -> Self {
+        let settings = TonemapSettings::default();
+        let uniform = TonemapUniform::from_settings(&settings, output_format, 0.0);
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tonemap Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let luminance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Luminance Buffer"),
+            contents: bytemuck::cast_slice(&[AutoExposure::default().middle_gray]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let (texture, view) = Self::create_texture(device, width, height);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tonemap Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("tonemap.wgsl"))),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Tonemap Bind Group Layout"),
+            entries: &[
+                hdr_texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+                uniform_entry(1, wgpu::ShaderStages::FRAGMENT),
+                storage_entry(2, wgpu::ShaderStages::FRAGMENT, true),
+            ],
+        });
+        let bind_group = Self::create_bind_group(device, &layout, &view, &uniform_buffer, &luminance_buffer);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tonemap Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tonemap Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(output_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let exposure = compute
+            .then(|| ExposurePasses::new(device, &view, &uniform_buffer, &luminance_buffer));
+
+        Self {
+            settings,
+            output_format,
+            time_delta: 0.0,
+            uniform_buffer,
+            luminance_buffer,
+            texture,
+            view,
+            layout,
+            bind_group,
+            pipeline,
+            exposure,
+        }
+    }
+
+    fn create_texture(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        uniform_buffer: &wgpu::Buffer,
+        luminance_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Tonemap Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: luminance_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Recreates the HDR target at a new size.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.texture, self.view) = Self::create_texture(device, width, height);
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.layout,
+            &self.view,
+            &self.uniform_buffer,
+            &self.luminance_buffer,
+        );
+        if let Some(exposure) = &mut self.exposure {
+            exposure.rebind(device, &self.view, &self.uniform_buffer, &self.luminance_buffer);
+        }
+    }
+
+    pub fn settings(&self) -> &TonemapSettings {
+        &self.settings
+    }
+
+    /// Whether [`TonemapSettings::auto_exposure`] can be enabled on this device.
+    pub fn supports_auto_exposure(&self) -> bool {
+        self.exposure.is_some()
+    }
+
+    /// Applies new settings. Returns `false`, changing nothing, if they enable
+    /// auto-exposure on a device without compute shaders.
+    ///
+    /// Turning auto-exposure on starts adapting from middle gray, i.e. from
+    /// the fixed exposure.
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: TonemapSettings) -> bool {
+        if settings.auto_exposure.is_some() && !self.supports_auto_exposure() {
+            return false;
+        }
+        let old = std::mem::replace(&mut self.settings, settings);
+        if let (None, Some(auto)) = (old.auto_exposure, settings.auto_exposure) {
+            queue.write_buffer(&self.luminance_buffer, 0, bytemuck::cast_slice(&[auto.middle_gray]));
+        }
+        self.write_uniform(queue);
+        true
+    }
+
+    /// Sets the time auto-exposure adapts over in each following frame.
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: Duration) {
+        self.time_delta = dt.as_secs_f32();
+        if self.settings.auto_exposure.is_some() {
+            self.write_uniform(queue);
+        }
+    }
+
+    fn write_uniform(&self, queue: &wgpu::Queue) {
+        let uniform = TonemapUniform::from_settings(&self.settings, self.output_format, self.time_delta);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// The HDR target to draw the scene into.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Records the exposure passes, if enabled, and the tonemapping pass
+    /// writing into `output`.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        if let (Some(exposure), Some(_)) = (&self.exposure, self.settings.auto_exposure) {
+            exposure.render(encoder, self.texture.width(), self.texture.height());
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+impl ExposurePasses {
+    fn new(
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        uniform_buffer: &wgpu::Buffer,
+        luminance_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Exposure Shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("exposure.wgsl"))),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Exposure Bind Group Layout"),
+            entries: &[
+                hdr_texture_entry(0, wgpu::ShaderStages::COMPUTE),
+                uniform_entry(1, wgpu::ShaderStages::COMPUTE),
+                storage_entry(2, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(3, wgpu::ShaderStages::COMPUTE, false),
+            ],
+        });
+        // Zero-initialized; the average pass clears it again after each frame.
+        let histogram_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Histogram Buffer"),
+            size: HISTOGRAM_BINS * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let bind_group =
+            Self::create_bind_group(device, &layout, view, uniform_buffer, &histogram_buffer, luminance_buffer);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Exposure Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        Self {
+            histogram_pipeline: compute_pipeline("Luminance Histogram Pipeline", "build_histogram"),
+            average_pipeline: compute_pipeline("Average Luminance Pipeline", "average_luminance"),
+            layout,
+            histogram_buffer,
+            bind_group,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        uniform_buffer: &wgpu::Buffer,
+        histogram_buffer: &wgpu::Buffer,
+        luminance_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Exposure Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: histogram_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: luminance_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn rebind(
+        &mut self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        uniform_buffer: &wgpu::Buffer,
+        luminance_buffer: &wgpu::Buffer,
+    ) {
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.layout,
+            view,
+            uniform_buffer,
+            &self.histogram_buffer,
+            luminance_buffer,
+        );
+    }
+
+    fn render(&self, encoder: &mut wgpu::CommandEncoder, width: u32, height: u32) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Exposure Pass"),
+        });
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.set_pipeline(&self.histogram_pipeline);
+        compute_pass.dispatch_workgroups(
+            width.div_ceil(HISTOGRAM_TILE),
+            height.div_ceil(HISTOGRAM_TILE),
+            1,
+        );
+        compute_pass.set_pipeline(&self.average_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+}
+
+fn hdr_texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn storage_entry(binding: u32, visibility: wgpu::ShaderStages, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
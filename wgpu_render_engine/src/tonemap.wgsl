@@ -0,0 +1,57 @@
+// `tone_mapping` is 0 for none, 1 for Reinhard and 2 for ACES.
+// `gamma_exponent` already accounts for sRGB targets encoding on write.
+struct TonemapUniform {
+    exposure: f32,
+    gamma_exponent: f32,
+    tone_mapping: u32,
+    auto_exposure: u32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation_rate: f32,
+    middle_gray: f32,
+    time_delta: f32,
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: TonemapUniform;
+@group(0) @binding(2) var<storage, read> adapted_luminance: f32;
+
+// One triangle covering the whole target.
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return out;
+}
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (color + vec3<f32>(1.0));
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let numerator = color * (2.51 * color + 0.03);
+    let denominator = color * (2.43 * color + 0.59) + 0.14;
+    return clamp(numerator / denominator, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// The HDR target matches the output size, so each fragment reads its own texel.
+@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(hdr_texture, vec2<i32>(in.clip_position.xy), 0).rgb;
+
+    var exposure = params.exposure;
+    if (params.auto_exposure != 0u) {
+        exposure *= params.middle_gray / max(adapted_luminance, 0.0001);
+    }
+    let color = hdr * exposure;
+
+    var mapped: vec3<f32>;
+    switch params.tone_mapping {
+        case 1u: { mapped = reinhard(color); }
+        case 2u: { mapped = aces(color); }
+        default: { mapped = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)); }
+    }
+    return vec4<f32>(pow(mapped, vec3<f32>(params.gamma_exponent)), 1.0);
+}
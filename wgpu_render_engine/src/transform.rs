@@ -0,0 +1,148 @@
+//! Per-object model and normal matrices in a single dynamically offset
+//! uniform buffer.
+
+use cgmath::{Matrix, Matrix3, Matrix4, SquareMatrix};
+
+crate::wgsl_struct! {
+    pub(crate) struct TransformUniform {
+        model: [[f32; 4]; 4],
+        /// Inverse transpose of the model matrix's upper 3x3, which keeps
+        /// normals perpendicular to surfaces under non-uniform scale.
+        normal: [[f32; 4]; 3],
+    }
+}
+
+const TRANSFORM_SIZE: wgpu::BufferAddress = std::mem::size_of::<TransformUniform>() as wgpu::BufferAddress;
+
+/// Model and normal matrices for every object drawn in a frame.
+///
+/// Each object's matrix lives in its own slot of one uniform buffer, padded to
+/// the device's `min_uniform_buffer_offset_alignment`. Draws select their slot
+/// with a dynamic offset, so every draw in a render pass sees its own matrix
+/// and the whole frame is uploaded with a single `write_buffer`. The buffer
+/// grows by doubling when more objects are added.
+pub struct TransformBuffer {
+    layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+    staging: Vec<u8>,
+}
+
+impl TransformBuffer {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = TRANSFORM_SIZE.div_ceil(alignment) * alignment;
+        let capacity = capacity.max(1);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Transform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(TRANSFORM_SIZE),
+                },
+                count: None,
+            }],
+        });
+        let (buffer, bind_group) = Self::create_buffer(device, &layout, stride, capacity);
+
+        Self {
+            layout,
+            buffer,
+            bind_group,
+            stride,
+            capacity,
+            staging: Vec::new(),
+        }
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        stride: wgpu::BufferAddress,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Transform Buffer"),
+            size: stride * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Transform Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(TRANSFORM_SIZE),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Number of slots written since the last [`TransformBuffer::clear`].
+    pub fn len(&self) -> usize {
+        self.staging.len() / self.stride as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staging.is_empty()
+    }
+
+    /// Forgets every slot, ready for the next frame's matrices.
+    pub fn clear(&mut self) {
+        self.staging.clear();
+    }
+
+    /// Appends `model` and its normal matrix and returns its slot index.
+    pub fn push(&mut self, model: Matrix4<f32>) -> usize {
+        let index = self.len();
+        let uniform = TransformUniform::new(model.into(), normal_matrix(model));
+        self.staging.extend_from_slice(bytemuck::bytes_of(&uniform));
+        self.staging.resize((index + 1) * self.stride as usize, 0);
+        index
+    }
+
+    /// The dynamic offset that selects slot `index` in `set_bind_group`.
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        (index as wgpu::BufferAddress * self.stride) as wgpu::DynamicOffset
+    }
+
+    /// Copies every slot to the GPU, reallocating the buffer first if it is too small.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.len() > self.capacity {
+            self.capacity = self.len().next_power_of_two();
+            let (buffer, bind_group) =
+                Self::create_buffer(device, &self.layout, self.stride, self.capacity);
+            self.buffer = buffer;
+            self.bind_group = bind_group;
+        }
+        if !self.staging.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &self.staging);
+        }
+    }
+}
+
+/// The inverse transpose of `model`'s upper 3x3 with each column padded for
+/// WGSL. A singular matrix, such as one with a zero scale, is used as is.
+fn normal_matrix(model: Matrix4<f32>) -> [[f32; 4]; 3] {
+    let linear = Matrix3::from_cols(model.x.truncate(), model.y.truncate(), model.z.truncate());
+    let normal = linear.invert().map_or(linear, |inverse| inverse.transpose());
+    [normal.x, normal.y, normal.z].map(|column| column.extend(0.0).into())
+}
@@ -0,0 +1,210 @@
+//! Host-side structs laid out like their WGSL counterparts.
+//!
+//! WGSL aligns and sizes types differently from Rust's `repr(C)`: a `vec3<f32>`
+//! is 16-byte aligned but only 12 bytes long, so a following `f32` packs into
+//! its tail. [`wgsl_struct!`] declares a `repr(C)` Pod struct and inserts the
+//! padding WGSL expects before every field and at the end, computed from each
+//! field type's [`WgslType`] alignment and size.
+
+/// Alignment and size of a type in WGSL's host-shareable memory layout.
+pub trait WgslType {
+    const ALIGN: usize;
+    const SIZE: usize;
+}
+
+macro_rules! impl_wgsl_type {
+    ($($ty:ty => ($align:expr, $size:expr)),* $(,)?) => {
+        $(impl WgslType for $ty {
+            const ALIGN: usize = $align;
+            const SIZE: usize = $size;
+        })*
+    };
+}
+
+impl_wgsl_type! {
+    f32 => (4, 4),
+    u32 => (4, 4),
+    i32 => (4, 4),
+    [f32; 2] => (8, 8),
+    [f32; 3] => (16, 12),
+    [f32; 4] => (16, 16),
+    // mat3x3<f32>: three columns, each padded to a vec4.
+    [[f32; 4]; 3] => (16, 48),
+    [[f32; 4]; 4] => (16, 64),
+}
+
+/// Stands in for a trailing runtime-sized `array<T>`. It takes no space in
+/// the host struct, which only covers the fixed-size head, but aligns the
+/// struct's end to where the elements start.
+pub type RuntimeArray<T> = std::marker::PhantomData<T>;
+
+impl<T: WgslType> WgslType for RuntimeArray<T> {
+    const ALIGN: usize = T::ALIGN;
+    const SIZE: usize = 0;
+}
+
+/// A struct declared with [`wgsl_struct!`], with its field offsets for checking
+/// against the shader.
+pub trait WgslLayout: WgslType {
+    /// The struct's name in WGSL.
+    const NAME: &'static str;
+    /// Each field's name and byte offset.
+    const FIELDS: &'static [(&'static str, usize)];
+}
+
+const fn round_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+/// Byte offset of field `index` given every field's `(align, size)`.
+pub const fn field_offset(fields: &[(usize, usize)], index: usize) -> usize {
+    let mut offset = 0;
+    let mut i = 0;
+    while i <= index {
+        if i > 0 {
+            offset += fields[i - 1].1;
+        }
+        offset = round_up(offset, fields[i].0);
+        i += 1;
+    }
+    offset
+}
+
+/// Padding inserted before field `index`.
+pub const fn padding_before(fields: &[(usize, usize)], index: usize) -> usize {
+    let end_of_previous = match index {
+        0 => 0,
+        _ => field_offset(fields, index - 1) + fields[index - 1].1,
+    };
+    field_offset(fields, index) - end_of_previous
+}
+
+/// Alignment of a struct: the largest alignment of its fields.
+pub const fn struct_align(fields: &[(usize, usize)]) -> usize {
+    let mut align = 1;
+    let mut i = 0;
+    while i < fields.len() {
+        if fields[i].0 > align {
+            align = fields[i].0;
+        }
+        i += 1;
+    }
+    align
+}
+
+/// Size of a struct: the end of its last field, rounded up to its alignment.
+pub const fn struct_size(fields: &[(usize, usize)]) -> usize {
+    let last = fields.len() - 1;
+    round_up(field_offset(fields, last) + fields[last].1, struct_align(fields))
+}
+
+/// Padding after the last field.
+pub const fn padding_after(fields: &[(usize, usize)]) -> usize {
+    let last = fields.len() - 1;
+    struct_size(fields) - field_offset(fields, last) - fields[last].1
+}
+
+/// Declares a `repr(C)` Pod struct whose layout matches the WGSL struct of the
+/// same name, along with a `new` constructor taking every field in order.
+///
+/// Every field type must implement [`WgslType`]. Padding fields are private
+/// and zeroed.
+#[macro_export]
+macro_rules! wgsl_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty),+ $(,)?
+        }
+    ) => {
+        $crate::__paste! {
+            #[allow(non_camel_case_types, dead_code)]
+            enum [<__ $name Field>] {
+                $($field),+
+            }
+
+            #[repr(C)]
+            #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+            $(#[$meta])*
+            $vis struct $name {
+                $(
+                    [<_pad_ $field>]: [u8; $crate::uniform::padding_before(
+                        $name::WGSL_FIELDS,
+                        [<__ $name Field>]::$field as usize,
+                    )],
+                    $(#[$field_meta])*
+                    $field_vis $field: $ty,
+                )+
+                _pad_end: [u8; $crate::uniform::padding_after($name::WGSL_FIELDS)],
+            }
+
+            impl $name {
+                /// Alignment and size of every field, in declaration order.
+                const WGSL_FIELDS: &'static [(usize, usize)] = &[
+                    $((<$ty as $crate::uniform::WgslType>::ALIGN, <$ty as $crate::uniform::WgslType>::SIZE)),+
+                ];
+
+                #[allow(clippy::too_many_arguments, dead_code)]
+                $vis fn new($($field: $ty),+) -> Self {
+                    Self {
+                        $([<_pad_ $field>]: [0; $crate::uniform::padding_before(
+                            $name::WGSL_FIELDS,
+                            [<__ $name Field>]::$field as usize,
+                        )], $field,)+
+                        _pad_end: [0; $crate::uniform::padding_after($name::WGSL_FIELDS)],
+                    }
+                }
+            }
+
+            impl $crate::uniform::WgslType for $name {
+                const ALIGN: usize = $crate::uniform::struct_align($name::WGSL_FIELDS);
+                const SIZE: usize = $crate::uniform::struct_size($name::WGSL_FIELDS);
+            }
+
+            impl $crate::uniform::WgslLayout for $name {
+                const NAME: &'static str = stringify!($name);
+                const FIELDS: &'static [(&'static str, usize)] = &[
+                    $((stringify!($field), std::mem::offset_of!($name, $field))),+
+                ];
+            }
+
+            const _: () = assert!(
+                std::mem::size_of::<$name>() == <$name as $crate::uniform::WgslType>::SIZE,
+                "host struct size differs from its WGSL layout",
+            );
+        }
+    };
+}
+
+/// Name, size and field offsets of a struct shared with a shader.
+#[derive(Debug, Clone, Copy)]
+pub struct UniformLayout {
+    pub name: &'static str,
+    pub size: usize,
+    pub fields: &'static [(&'static str, usize)],
+}
+
+impl UniformLayout {
+    pub fn of<T: WgslLayout>() -> Self {
+        Self {
+            name: T::NAME,
+            size: std::mem::size_of::<T>(),
+            fields: T::FIELDS,
+        }
+    }
+}
+
+/// Every host struct the engine uploads to its shaders.
+pub fn layouts() -> Vec<UniformLayout> {
+    vec![
+        UniformLayout::of::<crate::camera::CameraUniform>(),
+        UniformLayout::of::<crate::transform::TransformUniform>(),
+        UniformLayout::of::<crate::renderer::LightUniform>(),
+        UniformLayout::of::<crate::light::GpuLight>(),
+        UniformLayout::of::<crate::light::LightArray>(),
+        UniformLayout::of::<crate::material::MaterialUniform>(),
+        UniformLayout::of::<crate::tonemap::TonemapUniform>(),
+        UniformLayout::of::<crate::environment::PrefilterUniform>(),
+        UniformLayout::of::<crate::skybox::SkyUniform>(),
+    ]
+}
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3, Vector4};
+use wgpu_render_engine::{
+    Aabb, Action, Camera, CameraController, CameraMode, MeshData, OrthoView, Projection,
+};
+use winit::event::{ElementState, MouseScrollDelta};
+
+/// Holds W for `hold` seconds, then releases it, simulating `hz` frames per
+/// second for `total` seconds. Returns the camera's final position.
+fn simulate(controller: &mut CameraController, hz: u32, hold: f32, total: f32) -> Vector3<f32> {
+    let mut camera = Camera::new(800, 600);
+    let start = camera.position;
+    let dt = Duration::from_secs_f32(1.0 / hz as f32);
+    let frames = (total * hz as f32).round() as u32;
+    let released = (hold * hz as f32).round() as u32;
+    controller.process_action(Action::MoveForward, ElementState::Pressed);
+    for frame in 0..frames {
+        if frame == released {
+            controller.process_action(Action::MoveForward, ElementState::Released);
+        }
+        camera.update(controller, dt);
+    }
+    camera.position - start
+}
+
+#[test]
+fn speed_is_in_units_per_second() {
+    let mut controller = CameraController::new(4.0, 0.4);
+    let moved = simulate(&mut controller, 60, 1.0, 1.0);
+    assert!((moved.magnitude() - 4.0).abs() < 1e-3, "moved {:?}", moved);
+}
+
+#[test]
+fn smoothed_motion_matches_across_frame_rates() {
+    let mut controller = CameraController::new(4.0, 0.4).with_smoothing(8.0, 4.0);
+    let at_60 = simulate(&mut controller, 60, 0.5, 1.5);
+    let at_240 = simulate(&mut controller, 240, 0.5, 1.5);
+    assert!(at_60.magnitude() > 1.0);
+    assert!((at_60 - at_240).magnitude() < 0.05, "60 Hz {:?}, 240 Hz {:?}", at_60, at_240);
+}
+
+#[test]
+fn damping_slows_the_camera_to_a_stop() {
+    let mut controller = CameraController::new(4.0, 0.4).with_smoothing(8.0, 4.0);
+    let mut camera = Camera::new(800, 600);
+    controller.process_action(Action::MoveForward, ElementState::Pressed);
+    camera.update(&controller, Duration::from_secs(1));
+    controller.process_action(Action::MoveForward, ElementState::Released);
+
+    camera.update(&controller, Duration::from_millis(100));
+    let coasting = camera.velocity().magnitude();
+    assert!(coasting > 0.5 && coasting < 4.0, "velocity {}", coasting);
+    camera.update(&controller, Duration::from_secs(5));
+    assert!(camera.velocity().magnitude() < 1e-3);
+}
+
+#[test]
+fn mouse_movement_accumulates_within_a_frame() {
+    let mut controller = CameraController::new(4.0, 0.5);
+    controller.captured = true;
+    controller.process_mouse_movement(2.0, 1.0);
+    controller.process_mouse_movement(4.0, -3.0);
+    assert_eq!(controller.rotate_horizontal, 3.0);
+    assert_eq!(controller.rotate_vertical, 1.0);
+    controller.reset_mouse_movement();
+    assert_eq!(controller.rotate_horizontal, 0.0);
+}
+
+fn assert_close(a: Point3<f32>, b: Point3<f32>) {
+    assert!((a - b).magnitude() < 1e-3, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn switching_to_orbit_keeps_the_view() {
+    let mut camera = Camera::new(800, 600);
+    camera.look_to(Point3::new(3.0, 2.0, 3.0), Vector3::new(-1.0, -0.5, -1.0));
+    let (position, direction) = (camera.position, camera.direction);
+    camera.set_mode(CameraMode::Orbit);
+    camera.update(&CameraController::new(4.0, 0.4), Duration::from_millis(16));
+    assert_close(camera.position, position);
+    assert!((camera.direction - direction).magnitude() < 1e-3);
+    assert_close(camera.target, position + direction * camera.distance);
+}
+
+#[test]
+fn orbit_rotates_around_target_only_while_dragging() {
+    let mut camera = Camera::new(800, 600);
+    camera.set_mode(CameraMode::Orbit);
+    let target = camera.target;
+    let mut controller = CameraController::new(4.0, 0.5);
+
+    controller.process_mouse_movement(40.0, 0.0);
+    camera.update(&controller, Duration::from_millis(16));
+    controller.reset_mouse_movement();
+    assert_eq!(camera.yaw, -90.0);
+
+    controller.process_action(Action::Rotate, ElementState::Pressed);
+    controller.process_mouse_movement(40.0, 20.0);
+    camera.update(&controller, Duration::from_millis(16));
+    assert_eq!(camera.yaw, -70.0);
+    assert_close(camera.target, target);
+    assert!(((camera.position - camera.target).magnitude() - camera.distance).abs() < 1e-3);
+    assert!(camera.position.y > target.y);
+}
+
+#[test]
+fn scroll_zooms_orbit_distance() {
+    let mut camera = Camera::new(800, 600);
+    camera.set_mode(CameraMode::Orbit);
+    camera.distance = 10.0;
+    let mut controller = CameraController::new(4.0, 0.4);
+    controller.process_scroll(&MouseScrollDelta::LineDelta(0.0, 2.0));
+    camera.update(&controller, Duration::from_millis(16));
+    assert!((camera.distance - 8.1).abs() < 1e-4, "distance {}", camera.distance);
+    controller.reset_mouse_movement();
+    assert_eq!(controller.scroll, 0.0);
+}
+
+#[test]
+fn middle_drag_pans_target_in_view_plane() {
+    let mut camera = Camera::new(800, 600);
+    camera.set_mode(CameraMode::Orbit);
+    let target = camera.target;
+    let mut controller = CameraController::new(4.0, 0.4);
+    controller.process_action(Action::Pan, ElementState::Pressed);
+    controller.process_mouse_movement(100.0, 0.0);
+    assert_eq!(controller.rotate_horizontal, 0.0);
+    camera.update(&controller, Duration::from_millis(16));
+    // Facing -Z, dragging right moves the target left
+    assert!(camera.target.x < target.x);
+    assert!((camera.target.y - target.y).abs() < 1e-4);
+    assert_eq!(camera.yaw, -90.0);
+}
+
+#[test]
+fn frame_fits_bounds_in_view() {
+    let mut camera = Camera::new(800, 600);
+    let bounds = Aabb::from_points([Point3::new(9.0, -1.0, -1.0), Point3::new(11.0, 1.0, 1.0)]).unwrap();
+    camera.frame(&bounds);
+    assert_close(camera.target, Point3::new(10.0, 0.0, 0.0));
+    assert_close(camera.position, camera.target - camera.direction * camera.distance);
+    // The bounding sphere touches the edges of the 45 degree field of view
+    let radius = 3.0f32.sqrt();
+    assert!((camera.distance - radius / 22.5f32.to_radians().sin()).abs() < 1e-3);
+}
+
+#[test]
+fn mesh_bounds_cover_every_vertex() {
+    let bounds = MeshData::pyramid().bounds().unwrap();
+    let moved = bounds.transform(&Matrix4::from_translation(Vector3::new(0.0, 2.0, 0.0)));
+    assert_close(moved.min, bounds.min + Vector3::new(0.0, 2.0, 0.0));
+    assert_close(moved.max, bounds.max + Vector3::new(0.0, 2.0, 0.0));
+    assert!(MeshData::default().bounds().is_none());
+}
+
+/// Depth in 0..1 of a point `distance` in front of the camera.
+fn depth(projection: &Projection, distance: f32) -> f32 {
+    let clip = projection.matrix(1.5) * Vector4::new(0.0, 0.0, -distance, 1.0);
+    clip.z / clip.w
+}
+
+#[test]
+fn projections_map_clip_planes_to_wgpu_depth_range() {
+    let perspective = Projection::Perspective { fovy: 45.0, znear: 0.1, zfar: 500.0 };
+    assert!(depth(&perspective, 0.1).abs() < 1e-5);
+    assert!((depth(&perspective, 500.0) - 1.0).abs() < 1e-5);
+    assert!(!perspective.reverse_z());
+
+    let orthographic = Projection::Orthographic { height: 4.0, znear: 1.0, zfar: 11.0 };
+    assert!(depth(&orthographic, 1.0).abs() < 1e-6);
+    assert!((depth(&orthographic, 6.0) - 0.5).abs() < 1e-6);
+    let top = orthographic.matrix(1.5) * Vector4::new(3.0, 2.0, -5.0, 1.0);
+    assert_eq!((top.x, top.y, top.w), (1.0, 1.0, 1.0));
+
+    let infinite = Projection::ReverseZInfinite { fovy: 45.0, znear: 0.1 };
+    assert!((depth(&infinite, 0.1) - 1.0).abs() < 1e-6);
+    assert!(depth(&infinite, 1.0e6) > 0.0 && depth(&infinite, 1.0e6) < 1e-6);
+    assert!(depth(&infinite, 1000.0) > depth(&infinite, 2000.0));
+    assert!(infinite.reverse_z());
+    assert_eq!(infinite.zfar(), None);
+}
+
+#[test]
+fn ortho_views_look_along_axes() {
+    let mut camera = Camera::new(800, 600);
+    camera.target = Point3::new(1.0, 0.0, 0.0);
+    camera.distance = 5.0;
+    camera.set_ortho_view(OrthoView::Top);
+    assert_close(camera.position, Point3::new(1.0, 5.0, 0.0));
+    // Matches a 45 degree perspective at the target distance
+    match *camera.projection() {
+        Projection::Orthographic { height, .. } => {
+            assert!((height - 10.0 * 22.5f32.to_radians().tan()).abs() < 1e-4)
+        }
+        projection => panic!("expected orthographic, got {:?}", projection),
+    }
+    // Straight down is still a valid view, and survives an update without input
+    camera.update(&CameraController::new(4.0, 0.4), Duration::ZERO);
+    assert_close(camera.position, Point3::new(1.0, 5.0, 0.0));
+    let uniform = camera.build_view_projection_matrix();
+    assert!(bytemuck::cast_slice::<_, f32>(&[uniform]).iter().all(|v| v.is_finite()));
+
+    camera.set_ortho_view(OrthoView::Front);
+    assert_close(camera.position, Point3::new(1.0, 0.0, 5.0));
+    camera.set_ortho_view(OrthoView::Side);
+    assert_close(camera.position, Point3::new(6.0, 0.0, 0.0));
+}
+
+#[test]
+fn mouse_looks_only_while_button_held_or_captured() {
+    let mut controller = CameraController::new(4.0, 0.5);
+    controller.process_mouse_movement(10.0, 0.0);
+    assert_eq!(controller.rotate_horizontal, 0.0);
+    assert!(!controller.wants_cursor_grab());
+
+    assert!(controller.process_action(Action::Rotate, ElementState::Pressed));
+    assert!(controller.wants_cursor_grab());
+    controller.process_mouse_movement(10.0, 0.0);
+    assert_eq!(controller.rotate_horizontal, 5.0);
+    controller.process_action(Action::Rotate, ElementState::Released);
+    assert!(!controller.wants_cursor_grab());
+
+    // Capture toggles; releasing only applies while captured
+    assert!(controller.process_action(Action::ToggleCapture, ElementState::Pressed));
+    controller.process_action(Action::ToggleCapture, ElementState::Released);
+    assert!(controller.captured && controller.wants_cursor_grab());
+    controller.process_mouse_movement(10.0, 0.0);
+    assert_eq!(controller.rotate_horizontal, 10.0);
+    assert!(controller.process_action(Action::ReleaseCapture, ElementState::Pressed));
+    assert!(!controller.captured);
+    assert!(!controller.process_action(Action::ReleaseCapture, ElementState::Pressed));
+}
+
+#[test]
+fn release_clears_held_input() {
+    let mut controller = CameraController::new(4.0, 0.5);
+    controller.process_action(Action::MoveForward, ElementState::Pressed);
+    controller.process_action(Action::Rotate, ElementState::Pressed);
+    controller.captured = true;
+    controller.release();
+    assert_eq!(controller.amount_forward, 0.0);
+    assert!(!controller.rotating && !controller.captured);
+
+    let mut camera = Camera::new(800, 600);
+    let position = camera.position;
+    camera.update(&controller, Duration::from_millis(100));
+    assert_close(camera.position, position);
+}
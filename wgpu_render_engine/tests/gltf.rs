@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use cgmath::{InnerSpace, Vector3};
+use wgpu_render_engine::{
+    load_gltf, GltfError, GltfLightKind, GltfProjection, LightKind, SamplerSettings, Shading,
+};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/gltf").join(name)
+}
+
+#[test]
+fn loads_hierarchy_meshes_and_materials() {
+    let scene = load_gltf(fixture("scene.gltf")).unwrap();
+
+    assert_eq!(scene.roots, vec![0, 3, 4, 5]);
+    let root = &scene.nodes[0];
+    assert_eq!(root.name, "Root");
+    assert_eq!(root.children, vec![1, 2]);
+    assert_eq!(scene.nodes[2].parent, Some(0));
+    assert_eq!(root.transform.translation, Vector3::new(0.0, 0.0, -1.0));
+
+    let badge = &scene.materials[1];
+    assert_eq!(badge.base_color_factor, [0.2, 0.4, 0.9, 1.0]);
+    assert_eq!((badge.metallic_factor, badge.roughness_factor), (1.0, 0.3));
+    assert!(badge.double_sided);
+    assert_eq!(
+        badge.to_material().shading,
+        Shading::MetallicRoughness { metallic: 1.0, roughness: 0.3 }
+    );
+    assert_eq!(scene.materials[0].base_color_texture, Some(0));
+
+    let checker = &scene.textures[0];
+    assert_eq!((checker.width, checker.height), (2, 2));
+    assert_eq!(&checker.rgba[..8], &[255, 255, 255, 255, 64, 64, 64, 255]);
+    // NEAREST min and mag filters, default REPEAT along S, CLAMP_TO_EDGE along T.
+    assert_eq!(
+        checker.sampler,
+        SamplerSettings {
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..SamplerSettings::nearest()
+        }
+    );
+
+    // Vertex colors are COLOR_0 times the base color factor.
+    let box_primitive = &scene.meshes[0].primitives[0];
+    assert_eq!(box_primitive.data.indices.len(), 36);
+    assert!(box_primitive.data.vertices.iter().all(|v| v.color == [0.8, 0.3, 0.2]));
+    assert!(scene.meshes[1].primitives[0]
+        .data
+        .vertices
+        .iter()
+        .all(|v| v.color == [0.2, 0.4, 0.45]));
+}
+
+#[test]
+fn generates_normals_and_triangulates_fans() {
+    let scene = load_gltf(fixture("scene.gltf")).unwrap();
+
+    let box_data = &scene.meshes[0].primitives[0].data;
+    for vertex in &box_data.vertices {
+        // Flat faces of a centered box: generated normals point away from the center.
+        let normal = Vector3::from(vertex.normal);
+        let position = Vector3::from(vertex.position);
+        assert!((normal.magnitude() - 1.0).abs() < 1e-5);
+        assert!(normal.dot(position) > 0.0);
+    }
+
+    let fan = &scene.meshes[1].primitives[0].data;
+    assert_eq!(fan.indices, vec![0, 1, 2, 0, 2, 3]);
+}
+
+#[test]
+fn loads_cameras_and_lights() {
+    let scene = load_gltf(fixture("scene.gltf")).unwrap();
+
+    assert_eq!(scene.camera_nodes().collect::<Vec<_>>(), vec![3]);
+    match scene.cameras[0].projection {
+        GltfProjection::Perspective { yfov, znear, zfar, .. } => {
+            assert_eq!((yfov, znear, zfar), (0.8, 0.1, Some(100.0)));
+        }
+        projection => panic!("unexpected projection {:?}", projection),
+    }
+    let camera = scene.camera(3, 800, 600).unwrap();
+    assert_eq!(camera.position, cgmath::Point3::new(0.0, 1.0, 2.0));
+    assert!((camera.pitch - -25.0).abs() < 1e-3);
+    assert!(scene.camera(0, 800, 600).is_none());
+
+    assert_eq!(scene.light_nodes().collect::<Vec<_>>(), vec![4, 5]);
+    assert_eq!(scene.lights[0].kind, GltfLightKind::Directional);
+    assert_eq!(scene.lights[0].color, [1.0, 0.9, 0.8]);
+    assert_eq!(scene.lights[1].kind, GltfLightKind::Point);
+    assert_eq!(scene.lights[1].range, Some(10.0));
+}
+
+#[test]
+fn converts_lights_to_dynamic_lights() {
+    let scene = load_gltf(fixture("scene.gltf")).unwrap();
+
+    let sun = scene.light(4).unwrap();
+    assert_eq!(sun.kind, LightKind::Directional);
+    assert_eq!((sun.color, sun.intensity), ([1.0, 0.9, 0.8], 3.0));
+    // Tilted 60 degrees down from -Z.
+    assert!((sun.direction - Vector3::new(0.0, -0.866_025_4, -0.5)).magnitude() < 1e-5);
+
+    let lamp = scene.light(5).unwrap();
+    assert_eq!(lamp.kind, LightKind::Point);
+    assert_eq!(lamp.position, cgmath::Point3::new(0.0, 2.0, 0.0));
+    assert_eq!((lamp.intensity, lamp.range), (20.0, Some(10.0)));
+    assert!(scene.light(0).is_none());
+}
+
+#[test]
+fn glb_matches_gltf() {
+    let gltf = load_gltf(fixture("scene.gltf")).unwrap();
+    let glb = load_gltf(fixture("scene.glb")).unwrap();
+
+    assert_eq!(glb.nodes.len(), gltf.nodes.len());
+    assert_eq!(glb.materials, gltf.materials);
+    assert_eq!(glb.textures[0].rgba, gltf.textures[0].rgba);
+    for (a, b) in glb.meshes.iter().zip(&gltf.meshes) {
+        assert_eq!(a.primitives[0].data.indices, b.primitives[0].data.indices);
+    }
+}
+
+#[test]
+fn rejects_unsupported_required_extensions() {
+    match load_gltf(fixture("unsupported_extension.gltf")) {
+        Err(GltfError::UnsupportedExtension(name)) => assert_eq!(name, "KHR_draco_mesh_compression"),
+        other => panic!("expected an unsupported extension error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    assert!(matches!(load_gltf(fixture("missing.gltf")), Err(GltfError::Gltf(_))));
+}
+
+#[test]
+fn rejects_out_of_bounds_indices() {
+    match load_gltf(fixture("index_out_of_bounds.gltf")) {
+        Err(GltfError::IndexOutOfBounds { mesh, index }) => assert_eq!((mesh.as_str(), index), ("Triangle", 3)),
+        other => panic!("expected an index out of bounds error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn rejects_attributes_shorter_than_positions() {
+    match load_gltf(fixture("short_normals.gltf")) {
+        Err(GltfError::AttributeCountMismatch { attribute, count, positions, .. }) => {
+            assert_eq!((attribute, count, positions), ("NORMAL", 2, 3));
+        }
+        other => panic!("expected an attribute count error, got {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,776 @@
+//! Golden-image regression tests.
+//!
+//! Each test renders a fixture scene headlessly on the fallback adapter and
+//! compares it with a reference PNG in `tests/golden/`. On mismatch the actual
+//! frame and a diff image are written to `target/golden-diffs/`.
+//!
+//! Run with `UPDATE_GOLDEN=1 cargo test --test golden` to regenerate the
+//! references after an intentional change to the output. On machines without
+//! any adapter, set `SKIP_GPU_TESTS=1` to skip the suite instead of failing.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use wgpu_render_engine::{
+    load_gltf, load_obj, screenshot, Action, Atmosphere, AutoExposure, Binding, CameraMode, EnvironmentData,
+    Light, Material, MeshData, OrthoView, ProceduralSky, Renderer, SamplerSettings, ShadowSettings, Sky,
+    TextureData, ToneMapping, TonemapSettings, Transform,
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// Largest per-channel difference at which two pixels still match.
+const CHANNEL_TOLERANCE: u8 = 3;
+
+/// Fraction of pixels allowed to exceed the channel tolerance, to absorb
+/// rasterization differences along edges between driver versions.
+const MAX_MISMATCH_RATIO: f64 = 0.001;
+
+/// Software adapters are not reliably thread safe, so fixtures render one at a time.
+static GPU: Mutex<()> = Mutex::new(());
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+fn diff_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden-diffs")
+}
+
+/// Creates a headless renderer on the fallback adapter. Without one, returns
+/// `None` to skip the test if `SKIP_GPU_TESTS` is set and panics otherwise, so
+/// a missing adapter never passes as a green run.
+fn headless_renderer() -> Option<Renderer> {
+    match pollster::block_on(Renderer::new_headless(WIDTH, HEIGHT, true)) {
+        Ok(renderer) => Some(renderer),
+        Err(e) if std::env::var_os("SKIP_GPU_TESTS").is_some() => {
+            eprintln!("skipping golden test: {}", e);
+            None
+        }
+        Err(e) => panic!("no adapter for golden tests ({}); set SKIP_GPU_TESTS=1 to skip them", e),
+    }
+}
+
+fn read_png(path: &PathBuf) -> Option<Vec<u8>> {
+    let file = std::fs::File::open(path).ok()?;
+    let decoder = png::Decoder::new(file);
+    let mut reader = decoder.read_info().expect("reference is not a valid PNG");
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).expect("reference PNG is truncated");
+    assert_eq!(
+        (info.width, info.height, info.color_type),
+        (WIDTH, HEIGHT, png::ColorType::Rgba),
+        "reference {} has the wrong size or color type",
+        path.display()
+    );
+    pixels.truncate(info.buffer_size());
+    Some(pixels)
+}
+
+/// Highlights mismatching pixels in red over a darkened copy of the reference.
+fn diff_image(expected: &[u8], actual: &[u8]) -> (Vec<u8>, usize) {
+    let mut mismatches = 0;
+    let diff = expected
+        .chunks_exact(4)
+        .zip(actual.chunks_exact(4))
+        .flat_map(|(e, a)| {
+            let differs = e.iter().zip(a).any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE);
+            if differs {
+                mismatches += 1;
+                [255, 0, 0, 255]
+            } else {
+                [e[0] / 4, e[1] / 4, e[2] / 4, 255]
+            }
+        })
+        .collect();
+    (diff, mismatches)
+}
+
+/// Adds the demo ground plane with its grid material.
+fn add_ground(renderer: &mut Renderer) {
+    let ground = renderer.add_mesh(&MeshData::ground_plane());
+    let grid = renderer.add_material(Material::grid_ground());
+    renderer.set_mesh_material(ground, Some(grid));
+    renderer.scene_mut().add_node(None, Transform::default(), Some(ground));
+}
+
+/// The pyramid-on-ground scene from the `pyramid` example.
+fn pyramid_scene(renderer: &mut Renderer) {
+    let pyramid = renderer.add_mesh(&MeshData::pyramid());
+    let scene = renderer.scene_mut();
+    scene.add_node(None, Transform::from_translation(Vector3::new(0.0, 1.0, 0.0)), Some(pyramid));
+    add_ground(renderer);
+}
+
+/// Renders the fixture set up by `setup` and compares it with `tests/golden/<name>.png`.
+fn assert_golden(name: &str, setup: impl FnOnce(&mut Renderer)) {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    setup(&mut renderer);
+    renderer.update(Duration::ZERO);
+    let actual = renderer.read_pixels().expect("failed to read back frame");
+
+    let reference_path = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        screenshot::write_png(&reference_path, WIDTH, HEIGHT, &actual).unwrap();
+        return;
+    }
+
+    let expected = read_png(&reference_path).unwrap_or_else(|| {
+        panic!(
+            "missing reference {}; run with UPDATE_GOLDEN=1 to create it",
+            reference_path.display()
+        )
+    });
+
+    let (diff, mismatches) = diff_image(&expected, &actual);
+    let ratio = mismatches as f64 / (WIDTH * HEIGHT) as f64;
+    if ratio > MAX_MISMATCH_RATIO {
+        std::fs::create_dir_all(diff_dir()).unwrap();
+        let actual_path = diff_dir().join(format!("{}.actual.png", name));
+        let diff_path = diff_dir().join(format!("{}.diff.png", name));
+        screenshot::write_png(&actual_path, WIDTH, HEIGHT, &actual).unwrap();
+        screenshot::write_png(&diff_path, WIDTH, HEIGHT, &diff).unwrap();
+        panic!(
+            "{}: {} of {} pixels differ from the reference (see {} and {})",
+            name,
+            mismatches,
+            WIDTH * HEIGHT,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn default_scene() {
+    assert_golden("default_scene", pyramid_scene);
+}
+
+#[test]
+fn elevated_view() {
+    assert_golden("elevated_view", |renderer| {
+        pyramid_scene(renderer);
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 4.0, 6.0);
+        camera.pitch = -30.0;
+    });
+}
+
+#[test]
+fn side_view() {
+    assert_golden("side_view", |renderer| {
+        pyramid_scene(renderer);
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(5.0, 1.5, 0.0);
+        camera.yaw = 180.0;
+        camera.pitch = -10.0;
+    });
+}
+
+#[test]
+fn pyramid_shadow() {
+    assert_golden("pyramid_shadow", |renderer| {
+        pyramid_scene(renderer);
+        renderer.set_light([-2.0, 6.0, 1.0], [1.0, 1.0, 1.0]);
+        // A smaller map recreates the texture and rebinds the light group.
+        renderer.set_shadow_settings(ShadowSettings {
+            map_size: 512,
+            depth_bias: 4,
+            extent: 10.0,
+            ..ShadowSettings::default()
+        });
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 5.0, 5.0);
+        camera.pitch = -40.0;
+    });
+}
+
+#[test]
+fn dynamic_lights() {
+    assert_golden("dynamic_lights", |renderer| {
+        pyramid_scene(renderer);
+        renderer.set_light([5.0, 5.0, 5.0], [0.1, 0.1, 0.1]);
+
+        let red = renderer.add_light(Light::point(cgmath::Point3::new(0.0, 0.0, 0.0), [1.0, 0.2, 0.2], 4.0, Some(6.0)));
+        renderer.add_light(Light::spot(
+            cgmath::Point3::new(-3.0, 3.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            0.3,
+            0.5,
+            [0.2, 0.4, 1.0],
+            20.0,
+            None,
+        ));
+        renderer.add_light(Light::directional(Vector3::new(1.0, -1.0, 0.0), [0.3, 0.3, 0.2], 0.5));
+        let removed = renderer.add_light(Light::point(cgmath::Point3::new(3.0, 0.0, 0.0), [0.0, 1.0, 0.0], 50.0, None));
+        // Grows the light buffer past its initial capacity before removing one.
+        renderer.add_light(Light::point(cgmath::Point3::new(3.0, -1.0, -4.0), [1.0, 1.0, 0.0], 4.0, Some(3.0)));
+        assert!(renderer.remove_light(removed).is_some());
+        assert!(renderer.move_light(red, cgmath::Point3::new(2.0, -1.0, 0.0)));
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 4.0, 6.0);
+        camera.pitch = -30.0;
+    });
+}
+
+#[test]
+fn resized_target() {
+    assert_golden("resized_target", |renderer| {
+        pyramid_scene(renderer);
+        renderer.resize(winit::dpi::PhysicalSize::new(WIDTH / 2, HEIGHT / 2));
+        renderer.resize(winit::dpi::PhysicalSize::new(WIDTH, HEIGHT));
+        renderer.camera_mut().position = cgmath::Point3::new(-2.0, 0.5, 3.0);
+        renderer.camera_mut().yaw = -60.0;
+    });
+}
+
+#[test]
+fn orthographic_top_view() {
+    assert_golden("orthographic_top_view", |renderer| {
+        pyramid_scene(renderer);
+        let camera = renderer.camera_mut();
+        camera.target = cgmath::Point3::new(0.0, 0.0, 0.0);
+        camera.distance = 10.0;
+        camera.set_orthographic(6.0, 0.1, 100.0);
+        camera.set_ortho_view(OrthoView::Top);
+    });
+}
+
+#[test]
+fn reverse_z_far_scene() {
+    assert_golden("reverse_z_far_scene", |renderer| {
+        pyramid_scene(renderer);
+        let pyramid = renderer.add_mesh(&MeshData::pyramid());
+        renderer.scene_mut().add_node(
+            None,
+            Transform::from_translation(Vector3::new(-150.0, 0.0, -400.0)).with_scale(Vector3::new(80.0, 80.0, 80.0)),
+            Some(pyramid),
+        );
+        renderer.camera_mut().set_reverse_z_infinite(45.0, 0.1);
+    });
+}
+
+#[test]
+fn msaa() {
+    assert_golden("msaa", |renderer| {
+        pyramid_scene(renderer);
+        assert!(renderer.supported_sample_counts().contains(&4));
+        assert!(renderer.set_sample_count(4));
+        // Resizing recreates the multisampled targets
+        renderer.resize(winit::dpi::PhysicalSize::new(WIDTH / 2, HEIGHT / 2));
+        renderer.resize(winit::dpi::PhysicalSize::new(WIDTH, HEIGHT));
+    });
+}
+
+#[test]
+fn unsupported_sample_count_is_rejected() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    assert_eq!(renderer.supported_sample_counts().first(), Some(&1));
+    assert!(!renderer.set_sample_count(3));
+    assert!(!renderer.set_sample_count(16));
+    assert_eq!(renderer.sample_count(), 1);
+}
+
+#[test]
+fn reinhard_tonemapping() {
+    assert_golden("reinhard_tonemapping", |renderer| {
+        pyramid_scene(renderer);
+        // Bright enough to clip without tonemapping
+        renderer.set_light([5.0, 5.0, 5.0], [3.0, 3.0, 3.0]);
+        assert!(renderer.set_tonemap_settings(TonemapSettings {
+            tone_mapping: ToneMapping::Reinhard,
+            exposure: 1.5,
+            gamma: 2.4,
+            ..TonemapSettings::default()
+        }));
+    });
+}
+
+/// Mean of every color channel of a frame.
+fn mean_brightness(pixels: &[u8]) -> f64 {
+    let sum: u64 = pixels.chunks_exact(4).flat_map(|p| &p[..3]).map(|&c| c as u64).sum();
+    sum as f64 / (pixels.len() / 4 * 3) as f64
+}
+
+#[test]
+fn auto_exposure_adapts_to_scene_brightness() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    if !renderer.supports_auto_exposure() {
+        eprintln!("skipping auto-exposure test: compute shaders are unavailable");
+        return;
+    }
+    // The ground fills the frame, so its brightness scales with the light
+    pyramid_scene(&mut renderer);
+    let camera = renderer.camera_mut();
+    camera.target = cgmath::Point3::new(0.0, 0.0, 0.0);
+    camera.distance = 10.0;
+    camera.set_orthographic(6.0, 0.1, 100.0);
+    camera.set_ortho_view(OrthoView::Top);
+
+    let frame = |renderer: &mut Renderer, intensity: f32, dt: Duration| {
+        renderer.set_light([5.0, 5.0, 5.0], [intensity; 3]);
+        renderer.update(dt);
+        mean_brightness(&renderer.read_pixels().unwrap())
+    };
+    let fixed_dim = frame(&mut renderer, 0.1, Duration::ZERO);
+    let fixed_bright = frame(&mut renderer, 1.0, Duration::ZERO);
+    assert!(fixed_bright > fixed_dim * 2.0, "dim {}, bright {}", fixed_dim, fixed_bright);
+
+    assert!(renderer.set_tonemap_settings(TonemapSettings {
+        auto_exposure: Some(AutoExposure::default()),
+        ..TonemapSettings::default()
+    }));
+    // Adapting starts from the fixed exposure and takes time
+    let start = frame(&mut renderer, 0.1, Duration::ZERO);
+    assert!((start - fixed_dim).abs() < 1.0, "fixed {}, auto {}", fixed_dim, start);
+    let auto_dim = frame(&mut renderer, 0.1, Duration::from_secs(10));
+    let auto_bright = frame(&mut renderer, 1.0, Duration::from_secs(10));
+    assert!(auto_dim > fixed_dim * 2.0, "fixed {}, auto {}", fixed_dim, auto_dim);
+    assert!((auto_dim - auto_bright).abs() < 5.0, "dim {}, bright {}", auto_dim, auto_bright);
+}
+
+#[test]
+fn runtime_meshes() {
+    assert_golden("runtime_meshes", |renderer| {
+        pyramid_scene(renderer);
+
+        let mut pyramids = MeshData::pyramid();
+        pyramids.append(&MeshData::pyramid());
+        pyramids.vertices[16..].iter_mut().for_each(|v| v.position[0] += 1.5);
+        let pair = renderer.add_mesh(&pyramids);
+        for x in [-3.0, 2.0] {
+            let transform = Transform::from_translation(Vector3::new(x, -1.0, -2.0));
+            renderer.scene_mut().add_node(None, transform, Some(pair));
+        }
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 3.0, 6.0);
+        camera.pitch = -20.0;
+
+        let removed = renderer.add_mesh(&MeshData::pyramid());
+        let transform = Transform::default().with_scale(Vector3::new(3.0, 3.0, 3.0));
+        let node = renderer.scene_mut().add_node(None, transform, Some(removed));
+        renderer.scene_mut().remove_node(node);
+    });
+}
+
+#[test]
+fn scene_hierarchy() {
+    assert_golden("scene_hierarchy", |renderer| {
+        add_ground(renderer);
+        let pyramid = renderer.add_mesh(&MeshData::pyramid());
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 4.0, 7.0);
+        camera.pitch = -30.0;
+
+        let scene = renderer.scene_mut();
+        let spin = Quaternion::from_angle_y(Deg(30.0));
+        let parent = scene.add_node(None, Transform::default().with_rotation(spin), Some(pyramid));
+        let half = Vector3::new(0.5, 0.5, 0.5);
+        let child = scene.add_node(
+            Some(parent),
+            Transform::from_translation(Vector3::new(2.0, 0.0, 0.0)).with_scale(half),
+            Some(pyramid),
+        );
+        scene.add_node(
+            Some(child),
+            Transform::from_translation(Vector3::new(0.0, 2.0, 0.0)).with_scale(half),
+            Some(pyramid),
+        );
+        // Moving the parent after building the tree must carry the children along.
+        scene.set_local_transform(
+            parent,
+            Transform::from_translation(Vector3::new(-1.0, -0.5, 0.0)).with_rotation(spin),
+        );
+    });
+}
+
+#[test]
+fn obj_model() {
+    assert_golden("obj_model", |renderer| {
+        add_ground(renderer);
+
+        let cube = load_obj(fixture("obj/cube.obj")).unwrap();
+        let spin = Quaternion::from_angle_y(Deg(35.0));
+        let transform = Transform::from_translation(Vector3::new(0.0, 0.0, -1.0)).with_rotation(spin);
+        cube.add_to_scene(renderer, None, transform).unwrap();
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 1.5, 2.0);
+        camera.pitch = -30.0;
+    });
+}
+
+#[test]
+fn gltf_scene() {
+    assert_golden("gltf_scene", |renderer| {
+        add_ground(renderer);
+
+        let scene = load_gltf(fixture("gltf/scene.gltf")).unwrap();
+        scene.add_to_scene(renderer, None);
+        *renderer.camera_mut() = scene.camera(3, WIDTH, HEIGHT).unwrap();
+        assert!(scene.apply_light(5, renderer));
+    });
+}
+
+#[test]
+fn materials() {
+    assert_golden("materials", |renderer| {
+        add_ground(renderer);
+
+        // Entirely green, which the ground used to be detected by.
+        let mut green = MeshData::pyramid();
+        green.vertices.iter_mut().for_each(|v| v.color = [0.1, 0.8, 0.1]);
+        let green = renderer.add_mesh(&green);
+        let shiny = renderer.add_material(Material {
+            shininess: 128.0,
+            ..Material::default()
+        });
+        renderer.set_mesh_material(green, Some(shiny));
+
+        let tinted = renderer.add_mesh(&MeshData::pyramid());
+        let matte = renderer.add_material(Material {
+            base_color: [1.0, 0.5, 0.5],
+            specular_strength: 0.0,
+            ..Material::default()
+        });
+        renderer.set_mesh_material(tinted, Some(matte));
+
+        // Falls back to the default material once its own is removed.
+        let fallback = renderer.add_mesh(&MeshData::pyramid());
+        let removed = renderer.add_material(Material::grid_ground());
+        renderer.set_mesh_material(fallback, Some(removed));
+        assert!(renderer.remove_material(removed).is_some());
+
+        for (x, mesh) in [(-1.5, green), (0.0, tinted), (1.5, fallback)] {
+            let transform = Transform::from_translation(Vector3::new(x, -0.5, -1.0));
+            renderer.scene_mut().add_node(None, transform, Some(mesh));
+        }
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 2.0, 3.5);
+        camera.pitch = -20.0;
+    });
+}
+
+#[test]
+fn pbr_materials() {
+    assert_golden("pbr_materials", |renderer| {
+        add_ground(renderer);
+        let sphere = MeshData::sphere(32, 16);
+        // Dielectric on top, gold below, getting rougher to the right
+        for (y, base_color, metallic) in [(0.6, [0.8, 0.1, 0.1], 0.0), (-0.6, [1.0, 0.78, 0.34], 1.0)] {
+            for (x, roughness) in [(-1.2, 0.15), (0.0, 0.5), (1.2, 0.9)] {
+                let mesh = renderer.add_mesh(&sphere);
+                let material = renderer.add_material(Material::metallic_roughness(base_color, metallic, roughness));
+                renderer.set_mesh_material(mesh, Some(material));
+                let transform = Transform::from_translation(Vector3::new(x, y, -1.0));
+                renderer.scene_mut().add_node(None, transform, Some(mesh));
+            }
+        }
+        renderer.add_light(Light::point(cgmath::Point3::new(-2.0, 0.0, 1.0), [0.3, 0.5, 1.0], 3.0, None));
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 0.0, 3.0);
+    });
+}
+
+#[test]
+fn environment_lighting() {
+    assert_golden("environment_lighting", |renderer| {
+        let sky = EnvironmentData::load(fixture("textures/sky.hdr")).unwrap();
+        assert!(renderer.set_environment(&sky));
+        add_ground(renderer);
+        let sphere = MeshData::sphere(32, 16);
+        // Matte and blue from the sky above, then increasingly blurred reflections
+        for (x, metallic, roughness) in [(-1.8, 0.0, 1.0), (-0.6, 1.0, 0.05), (0.6, 1.0, 0.4), (1.8, 1.0, 0.8)] {
+            let mesh = renderer.add_mesh(&sphere);
+            let material = renderer.add_material(Material::metallic_roughness([0.9; 3], metallic, roughness));
+            renderer.set_mesh_material(mesh, Some(material));
+            let transform = Transform::from_translation(Vector3::new(x, 0.0, -1.0));
+            renderer.scene_mut().add_node(None, transform, Some(mesh));
+        }
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 0.0, 3.0);
+    });
+}
+
+#[test]
+fn environment_can_be_removed() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    if !renderer.supports_environment() {
+        eprintln!("skipping environment test: compute shaders are unavailable");
+        return;
+    }
+    pyramid_scene(&mut renderer);
+    renderer.update(Duration::ZERO);
+    let flat = renderer.read_pixels().unwrap();
+
+    let sky = EnvironmentData::load(fixture("textures/sky.hdr")).unwrap();
+    assert!(renderer.set_environment(&sky));
+    assert_eq!(renderer.environment().map(|e| e.cube_size()), Some(16));
+    renderer.update(Duration::ZERO);
+    assert_ne!(renderer.read_pixels().unwrap(), flat);
+
+    assert!(renderer.remove_environment().is_some());
+    renderer.update(Duration::ZERO);
+    assert_eq!(renderer.read_pixels().unwrap(), flat);
+}
+
+#[test]
+fn procedural_sky() {
+    assert_golden("procedural_sky", |renderer| {
+        pyramid_scene(renderer);
+        renderer.set_sky(Sky::Procedural(ProceduralSky {
+            sun_angular_radius: 2.0,
+            ..ProceduralSky::default()
+        }));
+        // Moving the camera does not move the sky
+        renderer.camera_mut().position = cgmath::Point3::new(0.0, 1.0, 4.0);
+    });
+}
+
+#[test]
+fn atmospheric_sky() {
+    assert_golden("atmospheric_sky", |renderer| {
+        pyramid_scene(renderer);
+        // A low sun reddens the horizon around it
+        renderer.set_sky(Sky::Procedural(ProceduralSky {
+            sun_direction: Vector3::new(0.4, 0.05, -1.0),
+            sun_angular_radius: 2.0,
+            atmosphere: Some(Atmosphere::default()),
+            ..ProceduralSky::default()
+        }));
+        // The sky is drawn at the far plane of a reverse-Z projection too
+        renderer.camera_mut().set_reverse_z_infinite(60.0, 0.1);
+    });
+}
+
+#[test]
+fn sky_image() {
+    assert_golden("sky_image", |renderer| {
+        pyramid_scene(renderer);
+        let sky = EnvironmentData::load(fixture("textures/sky.hdr")).unwrap();
+        renderer.set_sky_image(&sky);
+        assert!(renderer.set_sample_count(4));
+        // Looking past the pyramid towards the sun
+        let camera = renderer.camera_mut();
+        camera.set_perspective(75.0, 0.1, 100.0);
+        camera.position = cgmath::Point3::new(0.0, 1.0, -5.0);
+        camera.yaw = 70.0;
+        camera.pitch = 20.0;
+    });
+}
+
+#[test]
+fn environment_sky_needs_an_environment() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    pyramid_scene(&mut renderer);
+    renderer.update(Duration::ZERO);
+    let clear = renderer.read_pixels().unwrap();
+
+    renderer.set_sky(Sky::Environment);
+    renderer.update(Duration::ZERO);
+    assert_eq!(renderer.read_pixels().unwrap(), clear);
+
+    if !renderer.supports_environment() {
+        eprintln!("skipping environment sky test: compute shaders are unavailable");
+        return;
+    }
+    let sky = EnvironmentData::load(fixture("textures/sky.hdr")).unwrap();
+    assert!(renderer.set_environment(&sky));
+    renderer.update(Duration::ZERO);
+    let with_environment = renderer.read_pixels().unwrap();
+    assert_ne!(with_environment, clear);
+
+    // The sky follows the environment's intensity, like its lighting
+    renderer.set_environment_intensity(0.0);
+    renderer.set_light([5.0, 5.0, 5.0], [0.0; 3]);
+    renderer.update(Duration::ZERO);
+    let dark = renderer.read_pixels().unwrap();
+    assert!(mean_brightness(&dark) < 1.0, "brightness {}", mean_brightness(&dark));
+}
+
+#[test]
+fn textured_materials() {
+    assert_golden("textured_materials", |renderer| {
+        // A fine checkerboard repeated once per unit, so distant ground
+        // samples the generated mip levels.
+        let size = 16;
+        let rgba = (0..size * size)
+            .flat_map(|i| if (i % size + i / size) % 2 == 0 { [230; 4] } else { [60, 120, 60, 255] })
+            .collect();
+        let fine = TextureData {
+            width: size,
+            height: size,
+            rgba,
+        };
+        let fine = renderer.add_texture(&fine, SamplerSettings::default());
+        let ground = renderer.add_mesh(&MeshData::ground_plane());
+        let material = renderer.add_material(Material {
+            texture: Some(fine),
+            specular_strength: 0.2,
+            ..Material::default()
+        });
+        renderer.set_mesh_material(ground, Some(material));
+        renderer.scene_mut().add_node(None, Transform::default(), Some(ground));
+
+        let checker = TextureData::load(fixture("textures/checker.png")).unwrap();
+        let checker = renderer.add_texture(&checker, SamplerSettings::nearest());
+        let pyramid = renderer.add_mesh(&MeshData::pyramid());
+        let material = renderer.add_material(Material::default());
+        // Swapping in a texture rebinds the material.
+        let textured = Material {
+            texture: Some(checker),
+            ..Material::default()
+        };
+        assert!(renderer.set_material(material, textured));
+        renderer.set_mesh_material(pyramid, Some(material));
+        let transform = Transform::from_translation(Vector3::new(0.0, 0.0, -1.0))
+            .with_scale(Vector3::new(1.5, 1.5, 1.5));
+        renderer.scene_mut().add_node(None, transform, Some(pyramid));
+
+        let camera = renderer.camera_mut();
+        camera.position = cgmath::Point3::new(0.0, 1.0, 3.0);
+        camera.pitch = -10.0;
+    });
+}
+
+#[test]
+fn held_keys_toggle_only_once() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    let (tab, t) = (Binding::Key(VirtualKeyCode::Tab), Binding::Key(VirtualKeyCode::T));
+    renderer.action_map_mut().rebind(Action::ToggleCameraMode, [tab, t]);
+    assert_eq!(renderer.camera().mode(), CameraMode::Fly);
+    // winit repeats the press while the key is held.
+    for _ in 0..3 {
+        assert!(renderer.process_binding(tab, ElementState::Pressed));
+    }
+    assert_eq!(renderer.camera().mode(), CameraMode::Orbit);
+
+    // The action stays held while either of its keys is down.
+    renderer.process_binding(t, ElementState::Pressed);
+    renderer.process_binding(tab, ElementState::Released);
+    renderer.process_binding(tab, ElementState::Pressed);
+    assert_eq!(renderer.camera().mode(), CameraMode::Orbit);
+    renderer.process_binding(tab, ElementState::Released);
+    renderer.process_binding(t, ElementState::Released);
+    renderer.process_binding(tab, ElementState::Pressed);
+    assert_eq!(renderer.camera().mode(), CameraMode::Fly);
+
+    // Focus loss drops the held key, whose release never arrives.
+    renderer.release_input();
+    renderer.process_binding(tab, ElementState::Pressed);
+    assert_eq!(renderer.camera().mode(), CameraMode::Orbit);
+
+    let capture = Binding::Key(VirtualKeyCode::C);
+    for _ in 0..2 {
+        renderer.process_binding(capture, ElementState::Pressed);
+    }
+    assert!(renderer.camera_controller().captured);
+}
+
+#[test]
+fn removed_ids_do_not_refer_to_new_resources() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    let stale_mesh = renderer.add_mesh(&MeshData::pyramid());
+    renderer.remove_mesh(stale_mesh);
+    let mesh = renderer.add_mesh(&MeshData::pyramid());
+    assert!(renderer.mesh(stale_mesh).is_none() && renderer.mesh(mesh).is_some());
+    assert!(renderer.remove_mesh(stale_mesh).is_none());
+    assert!(!renderer.set_mesh_material(stale_mesh, None));
+
+    let texture_data = TextureData::load(fixture("textures/checker.png")).unwrap();
+    let stale_texture = renderer.add_texture(&texture_data, SamplerSettings::default());
+    renderer.remove_texture(stale_texture);
+    let texture = renderer.add_texture(&texture_data, SamplerSettings::default());
+    assert!(renderer.texture(stale_texture).is_none() && renderer.texture(texture).is_some());
+
+    let stale_material = renderer.add_material(Material::default());
+    renderer.remove_material(stale_material);
+    let material = renderer.add_material(Material {
+        texture: Some(texture),
+        ..Material::default()
+    });
+    assert!(renderer.material(stale_material).is_none());
+    assert!(!renderer.set_material(stale_material, Material::default()));
+    assert_eq!(renderer.material(material).unwrap().texture, Some(texture));
+
+    let stale_light = renderer.add_light(Light::point(cgmath::Point3::new(0.0, 1.0, 0.0), [1.0; 3], 1.0, None));
+    renderer.remove_light(stale_light);
+    let light = renderer.add_light(Light::directional(-Vector3::unit_y(), [1.0; 3], 1.0));
+    assert!(!renderer.move_light(stale_light, cgmath::Point3::new(5.0, 0.0, 0.0)));
+    assert!(renderer.light_mut(stale_light).is_none());
+    assert_eq!(renderer.light(light).unwrap().position, cgmath::Point3::new(0.0, 0.0, 0.0));
+    assert_eq!(renderer.lights().count(), 1);
+}
+
+#[test]
+fn non_uniform_scale_keeps_normals_perpendicular() {
+    let _guard = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(mut renderer) = headless_renderer() else {
+        return;
+    };
+    add_ground(&mut renderer);
+    let camera = renderer.camera_mut();
+    camera.position = cgmath::Point3::new(0.0, 2.0, 5.0);
+    camera.pitch = -20.0;
+    let mut pyramid = MeshData::pyramid();
+    pyramid.vertices.iter_mut().for_each(|v| v.color = [0.4; 3]);
+    pyramid.compute_normals();
+    let mut stretched = pyramid.clone();
+    stretched.vertices.iter_mut().for_each(|v| v.position[0] *= 3.0);
+    stretched.compute_normals();
+    let placement = Transform::from_translation(Vector3::new(0.0, -1.0, -1.0))
+        .with_rotation(Quaternion::from_angle_y(Deg(40.0)));
+
+    // The stretch baked into the vertices, then applied by the node instead.
+    let baked = renderer.add_mesh(&stretched);
+    let node = renderer.scene_mut().add_node(None, placement, Some(baked));
+    renderer.update(Duration::ZERO);
+    let expected = renderer.read_pixels().unwrap();
+    renderer.scene_mut().remove_node(node);
+
+    let scaled = renderer.add_mesh(&pyramid);
+    let transform = placement.with_scale(Vector3::new(3.0, 1.0, 1.0));
+    renderer.scene_mut().add_node(None, transform, Some(scaled));
+    renderer.update(Duration::ZERO);
+    let actual = renderer.read_pixels().unwrap();
+    let (_, mismatches) = diff_image(&expected, &actual);
+    assert!(
+        mismatches as f64 / ((WIDTH * HEIGHT) as f64) <= MAX_MISMATCH_RATIO,
+        "{} pixels differ",
+        mismatches
+    );
+}
@@ -0,0 +1,75 @@
+//! Reloading the main shader from disk on the fallback adapter.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use wgpu_render_engine::hot_reload;
+use wgpu_render_engine::{MeshData, Renderer, ShaderError, ToneMapping, TonemapSettings, Transform};
+
+const SHADER: &str = include_str!("../src/shader.wgsl");
+
+/// Writes `source` to `path` and moves its modification time forward, so the
+/// watcher sees a change even on file systems with coarse timestamps.
+fn write_shader(path: &Path, source: &str, generation: u64) {
+    std::fs::write(path, source).unwrap();
+    let file = std::fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 + generation))
+        .unwrap();
+}
+
+fn shader_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+#[test]
+fn rejects_invalid_wgsl_with_location() {
+    let broken = SHADER.replace("let normal = normalize(in.world_normal);", "let normal = ;");
+    let Err(ShaderError::Compile(message)) = hot_reload::validate(&broken, Path::new("shader.wgsl")) else {
+        panic!("broken shader was accepted");
+    };
+    assert!(message.contains("shader.wgsl"), "{}", message);
+    assert!(hot_reload::validate(SHADER, Path::new("shader.wgsl")).is_ok());
+}
+
+#[test]
+fn rebuilds_pipeline_and_keeps_old_one_on_error() {
+    let Ok(mut renderer) = pollster::block_on(Renderer::new_headless(64, 48, true)) else {
+        eprintln!("skipping hot reload test: no fallback adapter");
+        return;
+    };
+    let pyramid = renderer.add_mesh(&MeshData::pyramid());
+    renderer.scene_mut().add_node(None, Transform::default(), Some(pyramid));
+    // Pass the shader's output through unchanged, so colors can be compared exactly.
+    renderer.set_tonemap_settings(TonemapSettings {
+        tone_mapping: ToneMapping::None,
+        ..TonemapSettings::default()
+    });
+
+    let path = shader_path("hot_reload_shader.wgsl");
+    write_shader(&path, SHADER, 0);
+    renderer.watch_shader(&path).unwrap();
+    assert!(!renderer.reload_shader().unwrap());
+    renderer.update(Duration::ZERO);
+    let original = renderer.read_pixels().unwrap();
+
+    let broken = SHADER.replace("return vec4<f32>(final_color, 1.0);", "return final_color;");
+    write_shader(&path, &broken, 1);
+    assert!(matches!(renderer.reload_shader(), Err(ShaderError::Compile(_))));
+    assert_eq!(renderer.read_pixels().unwrap(), original);
+
+    // Valid WGSL, but its bindings do not match the pipeline layout.
+    let unbound = SHADER.replace("@group(3) @binding(0)", "@group(3) @binding(7)");
+    write_shader(&path, &unbound, 2);
+    assert!(matches!(renderer.reload_shader(), Err(ShaderError::Pipeline(_))));
+    assert_eq!(renderer.read_pixels().unwrap(), original);
+
+    let magenta = SHADER.replace(
+        "return vec4<f32>(final_color, 1.0);",
+        "return vec4<f32>(1.0, 0.0, 1.0, 1.0);",
+    );
+    write_shader(&path, &magenta, 3);
+    assert!(renderer.reload_shader().unwrap());
+    let pixels = renderer.read_pixels().unwrap();
+    assert!(pixels.chunks_exact(4).any(|p| p == [255, 0, 255, 255]));
+    assert!(!renderer.reload_shader().unwrap());
+}
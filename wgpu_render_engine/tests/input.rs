@@ -0,0 +1,72 @@
+use wgpu_render_engine::{Action, ActionMap, ActionMapError, Binding};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+#[test]
+fn default_bindings_match_classic_controls() {
+    let map = ActionMap::default();
+    assert_eq!(map.actions(VirtualKeyCode::W).collect::<Vec<_>>(), [Action::MoveForward]);
+    assert_eq!(map.actions(VirtualKeyCode::Up).collect::<Vec<_>>(), [Action::MoveForward]);
+    assert_eq!(map.actions(MouseButton::Left).collect::<Vec<_>>(), [Action::Rotate]);
+    assert_eq!(map.actions(VirtualKeyCode::Q).count(), 0);
+}
+
+#[test]
+fn toml_overrides_only_listed_actions() {
+    let map = ActionMap::from_toml(
+        r#"
+        move_forward = ["Z", "Up"]
+        move_left = ["Q"]
+        rotate = [{ mouse = "Right" }, "LAlt"]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(map.bindings(Action::MoveForward), [VirtualKeyCode::Z.into(), VirtualKeyCode::Up.into()]);
+    assert_eq!(map.actions(VirtualKeyCode::W).count(), 0);
+    assert_eq!(map.actions(VirtualKeyCode::Q).collect::<Vec<_>>(), [Action::MoveLeft]);
+    assert_eq!(
+        map.bindings(Action::Rotate),
+        [Binding::Mouse { mouse: MouseButton::Right }, Binding::Key(VirtualKeyCode::LAlt)]
+    );
+    // Untouched actions keep their defaults
+    assert_eq!(map.bindings(Action::MoveBackward), ActionMap::default().bindings(Action::MoveBackward));
+}
+
+#[test]
+fn toml_round_trips() {
+    let mut map = ActionMap::default();
+    map.bind(Action::Pan, MouseButton::Other(4));
+    let parsed = ActionMap::from_toml(&map.to_toml()).unwrap();
+    assert_eq!(parsed, map);
+}
+
+#[test]
+fn rejects_unknown_actions_and_keys() {
+    for source in ["jump = [\"Space\"]", "move_up = [\"NotAKey\"]", "move_up = \"Space\""] {
+        match ActionMap::from_toml(source) {
+            Err(ActionMapError::Parse(_)) => {}
+            other => panic!("expected a parse error for {:?}, got {:?}", source, other),
+        }
+    }
+    assert!(matches!(ActionMap::load("missing/bindings.toml"), Err(ActionMapError::Io { .. })));
+}
+
+#[test]
+fn rebinding_at_runtime() {
+    let mut map = ActionMap::default();
+    map.unbind(VirtualKeyCode::Space);
+    assert!(map.bindings(Action::MoveUp).is_empty());
+
+    map.bind(Action::MoveUp, VirtualKeyCode::E);
+    map.bind(Action::MoveUp, VirtualKeyCode::E);
+    assert_eq!(map.bindings(Action::MoveUp), [VirtualKeyCode::E.into()]);
+
+    map.rebind(Action::MoveForward, [VirtualKeyCode::Comma.into()]);
+    assert_eq!(map.actions(VirtualKeyCode::Comma).collect::<Vec<_>>(), [Action::MoveForward]);
+    assert_eq!(map.actions(VirtualKeyCode::W).count(), 0);
+}
+
+#[test]
+fn example_bindings_load() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/bindings.toml");
+    assert_eq!(ActionMap::load(path).unwrap(), ActionMap::default());
+}
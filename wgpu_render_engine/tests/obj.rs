@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use cgmath::{InnerSpace, Vector3};
+use wgpu_render_engine::obj::parse_obj;
+use wgpu_render_engine::{load_obj, ObjError};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/obj").join(name)
+}
+
+fn parse(source: &str) -> Result<wgpu_render_engine::ObjModel, ObjError> {
+    parse_obj(source, Path::new(""))
+}
+
+#[test]
+fn loads_cube_with_materials() {
+    let model = load_obj(fixture("cube.obj")).unwrap();
+
+    assert_eq!(model.materials.len(), 2);
+    let red = &model.materials[0];
+    assert_eq!(red.name, "Red");
+    assert_eq!(red.diffuse, [0.8, 0.1, 0.1]);
+    assert_eq!(red.shininess, 64.0);
+    assert_eq!(red.diffuse_texture, Some(fixture("textures/red.png")));
+    // `Tr` is ignored: the engine has no transparency.
+    assert_eq!(model.materials[1].diffuse, [0.1, 0.2, 0.9]);
+
+    assert_eq!(model.meshes.len(), 2);
+    let (sides, caps) = (&model.meshes[0], &model.meshes[1]);
+    assert_eq!((sides.name.as_str(), sides.material), ("Cube", Some(0)));
+    assert_eq!(caps.material, Some(1));
+    // Four quads and two quads, two triangles each.
+    assert_eq!(sides.data.indices.len(), 4 * 6);
+    assert_eq!(caps.data.indices.len(), 2 * 6);
+    assert!(sides.data.vertices.iter().all(|v| v.color == [0.8, 0.1, 0.1]));
+}
+
+#[test]
+fn generates_outward_normals_when_missing() {
+    let model = load_obj(fixture("cube.obj")).unwrap();
+    for mesh in &model.meshes {
+        for vertex in &mesh.data.vertices {
+            let normal = Vector3::from(vertex.normal);
+            let position = Vector3::from(vertex.position);
+            assert!((normal.magnitude() - 1.0).abs() < 1e-5);
+            assert!(normal.dot(position) > 0.0, "normal {:?} points inward", normal);
+        }
+    }
+}
+
+#[test]
+fn keeps_explicit_normals() {
+    let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 -1\nf 1//1 2//1 3//1\n").unwrap();
+    assert!(model.meshes[0].data.vertices.iter().all(|v| v.normal == [0.0, 0.0, -1.0]));
+}
+
+#[test]
+fn triangulates_concave_polygons() {
+    // An L shape in the XY plane, counter-clockwise seen from +Z.
+    let source = "
+        v 0 0 0
+        v 2 0 0
+        v 2 1 0
+        v 1 1 0
+        v 1 2 0
+        v 0 2 0
+        f 1 2 3 4 5 6
+    ";
+    let model = parse(source).unwrap();
+    let data = &model.meshes[0].data;
+    assert_eq!(data.indices.len(), 4 * 3);
+
+    let mut area = 0.0;
+    for triangle in data.indices.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(data.vertices[triangle[i] as usize].position));
+        let normal = (b - a).cross(c - a);
+        assert!(normal.z > 0.0, "triangle {:?} flipped winding", triangle);
+        area += normal.magnitude() / 2.0;
+    }
+    assert!((area - 3.0).abs() < 1e-5, "triangles cover {} instead of 3", area);
+}
+
+#[test]
+fn reports_malformed_input() {
+    assert!(matches!(
+        parse("v 0 0 zero\n"),
+        Err(ObjError::Syntax { line: 1, .. })
+    ));
+    assert!(matches!(
+        parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"),
+        Err(ObjError::IndexOutOfBounds { line: 4, index: 4 })
+    ));
+    assert!(matches!(
+        parse("v 0 0 0\nv 1 0 0\nf 1 2\n"),
+        Err(ObjError::DegenerateFace { line: 3 })
+    ));
+    assert!(matches!(
+        parse("usemtl Missing\n"),
+        Err(ObjError::UnknownMaterial { line: 1, .. })
+    ));
+    assert!(matches!(
+        load_obj(fixture("missing_mtl.obj")),
+        Err(ObjError::Io { .. })
+    ));
+    assert!(matches!(
+        load_obj(fixture("does_not_exist.obj")),
+        Err(ObjError::Io { .. })
+    ));
+    match wgpu_render_engine::obj::parse_mtl("Kd 1 0 0\n", Path::new("")) {
+        Err(ObjError::Syntax { line: 1, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn flips_texture_coordinates_to_top_left_origin() {
+    let model = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0.25\nvt 0.5\nf 1/1 2/2 3/3\n").unwrap();
+    let uvs: Vec<_> = model.meshes[0].data.vertices.iter().map(|v| v.tex_coords).collect();
+    assert_eq!(uvs, vec![[0.0, 1.0], [1.0, 0.75], [0.5, 1.0]]);
+}
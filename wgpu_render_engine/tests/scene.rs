@@ -0,0 +1,115 @@
+use cgmath::{Deg, InnerSpace, Matrix4, Quaternion, Rotation3, SquareMatrix, Vector3, Vector4};
+use wgpu_render_engine::{Scene, Transform};
+
+fn origin_of(matrix: Matrix4<f32>) -> Vector3<f32> {
+    let origin = matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    Vector3::new(origin.x, origin.y, origin.z)
+}
+
+fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!(
+        (actual - expected).magnitude() < 1e-5,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn world_matrices_compose_down_the_hierarchy() {
+    let mut scene = Scene::new();
+    let root = scene.add_node(
+        None,
+        Transform::from_translation(Vector3::new(1.0, 0.0, 0.0))
+            .with_rotation(Quaternion::from_angle_y(Deg(90.0))),
+        None,
+    );
+    let child = scene.add_node(
+        Some(root),
+        Transform::from_translation(Vector3::new(0.0, 0.0, 1.0))
+            .with_scale(Vector3::new(2.0, 2.0, 2.0)),
+        None,
+    );
+    let grandchild = scene.add_node(
+        Some(child),
+        Transform::from_translation(Vector3::new(0.0, 0.0, 1.0)),
+        None,
+    );
+    scene.update_world_matrices();
+
+    assert_near(origin_of(scene.node(root).unwrap().world_matrix()), Vector3::new(1.0, 0.0, 0.0));
+    assert_near(origin_of(scene.node(child).unwrap().world_matrix()), Vector3::new(2.0, 0.0, 0.0));
+    // The child's scale doubles the grandchild's offset.
+    assert_near(
+        origin_of(scene.node(grandchild).unwrap().world_matrix()),
+        Vector3::new(4.0, 0.0, 0.0),
+    );
+}
+
+#[test]
+fn moving_a_parent_updates_cached_descendants() {
+    let mut scene = Scene::new();
+    let root = scene.add_node(None, Transform::default(), None);
+    let child = scene.add_node(Some(root), Transform::from_translation(Vector3::unit_y()), None);
+    scene.update_world_matrices();
+    assert_near(origin_of(scene.node(child).unwrap().world_matrix()), Vector3::unit_y());
+
+    scene.set_local_transform(root, Transform::from_translation(Vector3::unit_x()));
+    // Cached until the next update.
+    assert_near(origin_of(scene.node(child).unwrap().world_matrix()), Vector3::unit_y());
+    scene.update_world_matrices();
+    assert_near(origin_of(scene.node(child).unwrap().world_matrix()), Vector3::new(1.0, 1.0, 0.0));
+}
+
+#[test]
+fn reparenting_rejects_cycles() {
+    let mut scene = Scene::new();
+    let a = scene.add_node(None, Transform::default(), None);
+    let b = scene.add_node(Some(a), Transform::default(), None);
+    let c = scene.add_node(Some(b), Transform::default(), None);
+
+    assert!(!scene.set_parent(a, Some(c)));
+    assert!(!scene.set_parent(a, Some(a)));
+    assert!(scene.set_parent(c, None));
+    assert_eq!(scene.roots(), &[a, c]);
+    assert_eq!(scene.node(b).unwrap().children(), &[]);
+    assert_eq!(scene.node(c).unwrap().parent(), None);
+}
+
+#[test]
+fn removing_a_node_removes_its_subtree() {
+    let mut scene = Scene::new();
+    let root = scene.add_node(None, Transform::default(), None);
+    let child = scene.add_node(Some(root), Transform::default(), None);
+    let grandchild = scene.add_node(Some(child), Transform::default(), None);
+    let other = scene.add_node(None, Transform::default(), None);
+
+    scene.remove_node(child);
+    assert!(scene.node(child).is_none());
+    assert!(scene.node(grandchild).is_none());
+    assert!(scene.node(root).unwrap().children().is_empty());
+    assert_eq!(scene.nodes().count(), 2);
+
+    scene.update_world_matrices();
+    assert_eq!(scene.node(other).unwrap().world_matrix(), Matrix4::identity());
+}
+
+#[test]
+fn removed_node_ids_stay_invalid_after_their_slot_is_reused() {
+    let mut scene = Scene::new();
+    let root = scene.add_node(None, Transform::default(), None);
+    let removed = scene.add_node(Some(root), Transform::default(), None);
+    scene.remove_node(removed);
+    let reused = scene.add_node(None, Transform::default(), None);
+
+    assert!(scene.node(removed).is_none());
+    assert!(!scene.set_parent(removed, Some(root)));
+    assert!(!scene.set_parent(reused, Some(removed)));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        scene.add_node(Some(removed), Transform::default(), None)
+    }));
+    assert!(result.is_err());
+    assert_eq!(scene.nodes().count(), 2);
+    assert!(scene.node(reused).unwrap().children().is_empty());
+    assert!(scene.node(root).unwrap().children().is_empty());
+}
@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use wgpu_render_engine::{EnvironmentData, TextureData, TextureError};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/textures").join(name)
+}
+
+#[test]
+fn loads_png_as_rgba8() {
+    let checker = TextureData::load(fixture("checker.png")).unwrap();
+    assert_eq!((checker.width, checker.height), (8, 4));
+    assert_eq!(checker.rgba.len(), 8 * 4 * 4);
+    assert_eq!(&checker.rgba[..8], &[240, 200, 40, 255, 30, 60, 160, 255]);
+    // Down to 1x1 from the longer side: 8, 4, 2, 1.
+    assert_eq!(checker.mip_level_count(), 4);
+}
+
+#[test]
+fn decodes_jpeg() {
+    let pixels = image::RgbImage::from_pixel(4, 2, image::Rgb([200, 40, 40]));
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 100)
+        .encode_image(&pixels)
+        .unwrap();
+
+    let texture = TextureData::decode(&jpeg).unwrap();
+    assert_eq!((texture.width, texture.height), (4, 2));
+    let [r, g, b, a] = [0, 1, 2, 3].map(|c| texture.rgba[c]);
+    assert!(r.abs_diff(200) <= 4 && g.abs_diff(40) <= 4 && b.abs_diff(40) <= 4);
+    assert_eq!(a, 255);
+}
+
+#[test]
+fn loads_hdr_environment_as_linear_floats() {
+    let sky = EnvironmentData::load(fixture("sky.hdr")).unwrap();
+    assert_eq!((sky.width, sky.height), (32, 16));
+    assert_eq!(sky.rgba.len(), 32 * 16 * 4);
+    // The sun is far brighter than an 8-bit texture could store.
+    let sun = (3 * 32 + 20) * 4;
+    assert!(sky.rgba[sun] > 15.0, "sun is {}", sky.rgba[sun]);
+    let ground = &sky.rgba[(15 * 32) * 4..][..4];
+    assert!((ground[0] - 0.25).abs() < 0.01 && (ground[2] - 0.1).abs() < 0.01);
+    assert_eq!(ground[3], 1.0);
+    assert_eq!(sky.cube_size(), 16);
+    assert!(matches!(EnvironmentData::decode(b"not an image"), Err(TextureError::Decode(_))));
+}
+
+#[test]
+fn halves_environments_until_they_fit() {
+    let sky = EnvironmentData::load(fixture("sky.hdr")).unwrap();
+    assert!(matches!(sky.fit_within(32), Cow::Borrowed(_)));
+
+    let small = sky.fit_within(10);
+    assert_eq!((small.width, small.height), (8, 4));
+    assert_eq!(small.rgba.len(), 8 * 4 * 4);
+    // Each texel averages a 4x4 block, keeping the total energy.
+    let total = |data: &EnvironmentData| data.rgba.chunks(4).map(|texel| texel[0]).sum::<f32>();
+    assert!((total(&small) * 16.0 - total(&sky)).abs() < 1e-2 * total(&sky));
+    assert_eq!(small.rgba[3], 1.0);
+}
+
+#[test]
+fn reports_missing_and_corrupt_files() {
+    let missing = TextureData::load(fixture("missing.png")).unwrap_err();
+    assert!(matches!(missing, TextureError::Io { .. }));
+    let corrupt = TextureData::decode(b"not an image").unwrap_err();
+    assert!(matches!(corrupt, TextureError::Decode(_)));
+}
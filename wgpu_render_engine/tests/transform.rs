@@ -0,0 +1,40 @@
+use cgmath::{Matrix4, Vector3};
+use wgpu_render_engine::TransformBuffer;
+
+fn fallback_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        force_fallback_adapter: true,
+        compatible_surface: None,
+    }))?;
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()
+}
+
+#[test]
+fn thousands_of_objects_get_distinct_aligned_slots() {
+    let Some((device, queue)) = fallback_device() else {
+        eprintln!("skipping: no fallback adapter");
+        return;
+    };
+    let alignment = device.limits().min_uniform_buffer_offset_alignment;
+    let mut transforms = TransformBuffer::new(&device, 2);
+
+    for i in 0..5000 {
+        let index = transforms.push(Matrix4::from_translation(Vector3::new(i as f32, 0.0, 0.0)));
+        assert_eq!(index, i);
+    }
+    transforms.upload(&device, &queue);
+
+    assert_eq!(transforms.len(), 5000);
+    assert_eq!(transforms.offset(0), 0);
+    assert!(transforms.offset(1) >= 64);
+    assert_eq!(transforms.offset(1) % alignment, 0);
+    assert_eq!(transforms.offset(4999), 4999 * transforms.offset(1));
+
+    transforms.clear();
+    assert!(transforms.is_empty());
+    transforms.push(Matrix4::from_scale(2.0));
+    transforms.upload(&device, &queue);
+    device.poll(wgpu::Maintain::Wait);
+}
@@ -0,0 +1,89 @@
+//! Checks every host struct shared with a shader against the layout naga
+//! reflects from the WGSL source.
+
+use wgpu_render_engine::uniform::{self, UniformLayout};
+
+const SHADERS: &[(&str, &str)] = &[
+    ("shader.wgsl", include_str!("../src/shader.wgsl")),
+    ("shadow.wgsl", include_str!("../src/shadow.wgsl")),
+    ("tonemap.wgsl", include_str!("../src/tonemap.wgsl")),
+    ("exposure.wgsl", include_str!("../src/exposure.wgsl")),
+    ("skybox.wgsl", include_str!("../src/skybox.wgsl")),
+    ("ibl.wgsl", include_str!("../src/ibl.wgsl")),
+];
+
+/// Size and `(name, offset)` of every member of the WGSL struct `name`. A
+/// struct ending in a runtime-sized array is sized up to where the array
+/// starts.
+fn reflect(module: &naga::Module, name: &str) -> Option<(u32, Vec<(String, u32)>)> {
+    module.types.iter().find_map(|(_, ty)| match &ty.inner {
+        naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => {
+            let runtime_array = members.last().filter(|member| {
+                matches!(
+                    module.types[member.ty].inner,
+                    naga::TypeInner::Array { size: naga::ArraySize::Dynamic, .. }
+                )
+            });
+            let span = runtime_array.map_or(*span, |member| member.offset);
+            let members = members
+                .iter()
+                .map(|member| (member.name.clone().unwrap_or_default(), member.offset))
+                .collect();
+            Some((span, members))
+        }
+        _ => None,
+    })
+}
+
+fn check(file: &str, module: &naga::Module, layout: &UniformLayout) -> bool {
+    let Some((span, members)) = reflect(module, layout.name) else {
+        return false;
+    };
+    assert_eq!(
+        layout.size, span as usize,
+        "{}: size of {} differs from WGSL",
+        file, layout.name
+    );
+    let host: Vec<_> = layout
+        .fields
+        .iter()
+        .map(|&(name, offset)| (name.to_string(), offset as u32))
+        .collect();
+    assert_eq!(host, members, "{}: fields of {} differ from WGSL", file, layout.name);
+    true
+}
+
+#[test]
+fn host_uniforms_match_wgsl_layout() {
+    let modules: Vec<_> = SHADERS
+        .iter()
+        .map(|&(file, source)| {
+            let module = naga::front::wgsl::parse_str(source)
+                .unwrap_or_else(|e| panic!("{}: {}", file, e.emit_to_string(source)));
+            (file, module)
+        })
+        .collect();
+
+    for layout in uniform::layouts() {
+        let mut found = false;
+        for (file, module) in &modules {
+            found |= check(file, module, &layout);
+        }
+        assert!(found, "{} is not declared in any shader", layout.name);
+    }
+}
+
+#[test]
+fn vec3_followed_by_scalar_packs_into_its_tail() {
+    wgpu_render_engine::wgsl_struct! {
+        struct Packed {
+            color: [f32; 3],
+            ambient: f32,
+            position: [f32; 3],
+            matrix: [[f32; 4]; 4],
+        }
+    }
+    let fields = <Packed as uniform::WgslLayout>::FIELDS;
+    assert_eq!(fields, &[("color", 0), ("ambient", 12), ("position", 16), ("matrix", 32)]);
+    assert_eq!(std::mem::size_of::<Packed>(), 96);
+}